@@ -1,6 +1,10 @@
+#![allow(clippy::assign_op_pattern)]
+
 use std::ops::{Index, IndexMut};
 use board::mailbox::Mailbox;
 use constants::*;
+use types::Position;
+use util::is_white;
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct BitBoard(pub [u64; 0x10]);
@@ -39,6 +43,26 @@ impl BitBoard {
 
         self[ALL] = self[ALL_WHITE] | self[ALL_BLACK]
     }
+
+    // Flips a piece's bit on a square along with the occupancy boards.  Called once to take a
+    // piece off and again to put one on.
+    pub fn toggle(&mut self, piece_type: PieceType, pos: Position) {
+        let bit = 1u64 << square_index(pos);
+        let side = if is_white(piece_type) { ALL_WHITE } else { ALL_BLACK };
+
+        self[piece_type] ^= bit;
+        self[side] ^= bit;
+        self[ALL] ^= bit;
+    }
+}
+
+// a1 = 0, h1 = 7, ..., h8 = 63
+pub fn square_index(pos: Position) -> usize {
+    (pos.1 as usize) * 8 + pos.0 as usize
+}
+
+pub fn square_to_pos(square: usize) -> Position {
+    Position((square % 8) as File, (square / 8) as Rank)
 }
 
 impl Index<u8> for BitBoard {
@@ -0,0 +1,142 @@
+// Positions that are drawn whatever the moves from here, by the rules rather than by the
+// evaluation.
+
+use constants::*;
+use moves::generation::is_color_checkmated;
+use moves::types::Move;
+
+use super::Board;
+
+// bit set for every square the same colour as a1
+const DARK_SQUARES: u64 = 0xaa55_aa55_aa55_aa55;
+
+// the fifty-move rule counts moves by both sides
+const FIFTY_MOVES: u16 = 100;
+
+impl Board {
+    // Whether this position has already come up with the same side to move.  Only positions since
+    // the last irreversible move can match, and a null move from the search counts as one since
+    // it couldn't have happened in a real game.
+    //
+    // A single repetition is enough for the search to call it a draw: if repeating was good for
+    // one side once, it will be again.
+    pub fn is_repetition(&self) -> bool {
+        let reversible = (self.halfmove_counter as usize).min(self.history.len());
+
+        for plies_ago in 1..reversible + 1 {
+            let context = &self.history[self.history.len() - plies_ago];
+
+            if context.pending_move == Move::NULL {
+                return false;
+            }
+
+            if plies_ago % 2 == 0 && context.zhash == self.zhash {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // Fifty moves each without a capture or a pawn move, unless the last of them was mate.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.halfmove_counter >= FIFTY_MOVES && !is_color_checkmated(self, self.to_move)
+    }
+
+    // Neither side has enough left to ever mate: bare kings, a single minor piece, or bishops that
+    // are all on the same colour of square.
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy = [W_PAWN, W_ROOK, W_QUEEN, B_PAWN, B_ROOK, B_QUEEN].iter()
+            .any(|&piece| self.bb[piece] != 0);
+
+        if heavy {
+            return false;
+        }
+
+        let knights = self.bb[W_KNIGHT] | self.bb[B_KNIGHT];
+        let bishops = self.bb[W_BISHOP] | self.bb[B_BISHOP];
+        let minors = (knights | bishops).count_ones();
+
+        if minors <= 1 {
+            return true;
+        }
+
+        knights == 0 && (bishops & DARK_SQUARES == 0 || bishops & !DARK_SQUARES == 0)
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use moves::make_move::make_move;
+
+    #[allow(dead_code)]
+    fn play(board: &mut Board, pcns: &str) {
+        for pcn in pcns.split_whitespace() {
+            let mv = Move::from_pcn_string(pcn, board);
+            make_move(board, mv);
+        }
+    }
+
+    #[test]
+    fn knights_shuffling_repeat() {
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/4P3/1N2K1n1 w - - 0 1");
+        assert!(!board.is_repetition());
+
+        play(&mut board, "b1c3 g1f3 c3b1");
+        assert!(!board.is_repetition());
+
+        play(&mut board, "f3g1");
+        assert!(board.is_repetition());
+
+        // the shuffle starts over once the pawn has moved
+        play(&mut board, "e2e3 g1f3 b1c3 f3g1");
+        assert!(!board.is_repetition());
+        assert_eq!(board.halfmove_counter, 3);
+        play(&mut board, "c3b1");
+        assert!(board.is_repetition());
+    }
+
+    #[test]
+    fn null_moves_break_repetitions() {
+        use moves::make_move::make_null_move;
+
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        play(&mut board, "a1a2 e8d8 a2a1 d8e8");
+        assert!(board.is_repetition());
+
+        // back to the start with white to move, but only by passing twice
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        play(&mut board, "a1a2");
+        make_null_move(&mut board);
+        play(&mut board, "a2a1");
+        make_null_move(&mut board);
+        assert!(!board.is_repetition());
+    }
+
+    #[test]
+    fn fifty_moves_unless_mated() {
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").is_fifty_move_draw());
+        assert!(!Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").is_fifty_move_draw());
+
+        // mate on the hundredth ply still counts
+        assert!(!Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 80").is_fifty_move_draw());
+    }
+
+    #[test]
+    fn insufficient_material() {
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_insufficient_material());
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/3NK3 w - - 0 1").is_insufficient_material());
+        assert!(Board::from_fen("4kb2/8/8/8/8/8/8/4K3 w - - 0 1").is_insufficient_material());
+
+        // bishops on the same colour can never cover the other one
+        assert!(Board::from_fen("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1").is_insufficient_material());
+        assert!(!Board::from_fen("4k1b1/8/8/8/8/8/8/2B1K3 w - - 0 1").is_insufficient_material());
+
+        assert!(!Board::from_fen("4k3/8/8/8/8/8/8/2NNK3 w - - 0 1").is_insufficient_material());
+        assert!(!Board::from_fen("4k3/8/8/8/8/8/8/2BNK3 w - - 0 1").is_insufficient_material());
+        assert!(!Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").is_insufficient_material());
+        assert!(!Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").is_insufficient_material());
+    }
+}
@@ -0,0 +1,227 @@
+use std::error::Error;
+use std::fmt;
+
+use constants::*;
+use types::*;
+use util::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    // FENs have six fields, EPDs only the first four
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+
+    // a rank that describes more or fewer than eight squares; ranks are numbered from 1
+    RankOverflow(Rank),
+    RankUnderflow(Rank),
+
+    UnknownPiece(char),
+
+    // each side needs exactly one king, and pawns can't stand on the first or eighth rank
+    KingCount(Color, usize),
+    PawnOnBackRank(Rank),
+
+    BadSideToMove(String),
+    BadCastling(String),
+    BadEnPassant(String),
+    BadCounter(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FenError::WrongFieldCount(n) => write!(f, "expected 4 or 6 fields, found {}", n),
+            FenError::WrongRankCount(n) => write!(f, "expected 8 ranks, found {}", n),
+            FenError::RankOverflow(r) => write!(f, "rank {} describes more than 8 squares", r),
+            FenError::RankUnderflow(r) => write!(f, "rank {} describes fewer than 8 squares", r),
+            FenError::UnknownPiece(c) => write!(f, "unknown piece '{}'", c),
+            FenError::KingCount(color, n) => {
+                write!(f, "expected one {} king, found {}", if color == WHITE { "white" } else { "black" }, n)
+            },
+            FenError::PawnOnBackRank(r) => write!(f, "pawn on rank {}", r),
+            FenError::BadSideToMove(ref s) => write!(f, "bad side to move '{}'", s),
+            FenError::BadCastling(ref s) => write!(f, "bad castling field '{}'", s),
+            FenError::BadEnPassant(ref s) => write!(f, "bad en passant square '{}'", s),
+            FenError::BadCounter(ref s) => write!(f, "bad move counter '{}'", s),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+pub fn try_parse_fen_pieces(piece_str: &str) -> Result<PieceList, FenError> {
+    let ranks = piece_str.split('/').collect::<Vec<&str>>();
+    if ranks.len() != RANK_COUNT as usize {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+
+    let mut pieces: PieceList = vec![];
+
+    for (rank_pos, rank_str) in ranks.into_iter().enumerate() {
+        let r: Rank = 7 - rank_pos as Rank;
+        let mut f: File = 0;
+
+        for c in rank_str.chars() {
+            if f >= FILE_COUNT {
+                return Err(FenError::RankOverflow(r + 1));
+            }
+
+            if let Some(digit) = c.to_digit(10) {
+                if digit == 0 || digit > 8 {
+                    return Err(FenError::UnknownPiece(c));
+                }
+
+                f += digit as File;
+            } else if "PNBRQKpnbrqk".contains(c) {
+                pieces.push(PiecePosition(char_to_piece_type(&c), f, r));
+                f += 1;
+            } else {
+                return Err(FenError::UnknownPiece(c));
+            }
+        }
+
+        if f > FILE_COUNT {
+            return Err(FenError::RankOverflow(r + 1));
+        } else if f < FILE_COUNT {
+            return Err(FenError::RankUnderflow(r + 1));
+        }
+    }
+
+    Ok(pieces)
+}
+
+// Positions the rest of the engine can't cope with, even though every square was described.
+pub fn check_pieces(pieces: &PieceList) -> Result<(), FenError> {
+    for &color in [WHITE, BLACK].iter() {
+        let kings = pieces.iter().filter(|piece| piece.0 == to_color(W_KING, color == WHITE)).count();
+        if kings != 1 {
+            return Err(FenError::KingCount(color, kings));
+        }
+    }
+
+    match pieces.iter().find(|piece| to_white(piece.0) == PAWN && (piece.2 == 0 || piece.2 == RANK_COUNT - 1)) {
+        Some(pawn) => Err(FenError::PawnOnBackRank(pawn.2 + 1)),
+        None => Ok(()),
+    }
+}
+
+pub fn parse_side_to_move(s: &str) -> Result<Color, FenError> {
+    match s {
+        "w" => Ok(WHITE),
+        "b" => Ok(BLACK),
+        _ => Err(FenError::BadSideToMove(s.to_string())),
+    }
+}
+
+pub fn parse_castling(s: &str) -> Result<u8, FenError> {
+    if s == "-" {
+        return Ok(0);
+    }
+
+    let mut castling = 0;
+    for c in s.chars() {
+        let flag = match c {
+            'K' => W_OO,
+            'Q' => W_OOO,
+            'k' => B_OO,
+            'q' => B_OOO,
+            _ => 0
+        };
+
+        // unknown letters and repeats are both errors
+        if flag == 0 || castling & flag != 0 {
+            return Err(FenError::BadCastling(s.to_string()));
+        }
+
+        castling |= flag;
+    }
+
+    if castling == 0 {
+        return Err(FenError::BadCastling(s.to_string()));
+    }
+
+    Ok(castling)
+}
+
+// Only the file is kept, but the rank still has to be the one behind a pawn that just double
+// pushed.
+pub fn parse_en_passant(s: &str, to_move: Color) -> Result<File, FenError> {
+    if s == "-" {
+        return Ok(NO_EN_PASSANT);
+    }
+
+    let chars = s.chars().collect::<Vec<char>>();
+    let expected_rank = if to_move == WHITE { '6' } else { '3' };
+
+    if chars.len() != 2 || chars[1] != expected_rank {
+        return Err(FenError::BadEnPassant(s.to_string()));
+    }
+
+    match char_to_file(&chars[0].to_string()) {
+        NO_EN_PASSANT => Err(FenError::BadEnPassant(s.to_string())),
+        file => Ok(file),
+    }
+}
+
+// Any number is a counter; one too big for the board's just stops at the most it can hold.
+pub fn parse_counter(s: &str) -> Result<u16, FenError> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+        return Err(FenError::BadCounter(s.to_string()));
+    }
+
+    Ok(s.parse::<u16>().unwrap_or(u16::MAX))
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use board::Board;
+
+    #[test]
+    fn fen_errors() {
+        let cases = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -  0", FenError::WrongFieldCount(5)),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1", FenError::WrongRankCount(7)),
+            ("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::RankOverflow(7)),
+            ("rnbqkbnr/pppppppp/8/8/8/44/PPPPPPPP/RNBQKBN1R w KQkq - 0 1", FenError::RankOverflow(1)),
+            ("rnbqkbnr/pppppppp/8/8/8/7/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::RankUnderflow(3)),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1", FenError::UnknownPiece('X')),
+            ("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1", FenError::KingCount(BLACK, 0)),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBKKBNR w kq - 0 1", FenError::KingCount(WHITE, 2)),
+            ("4k3/8/8/8/8/8/8/8 w - - 0 1", FenError::KingCount(WHITE, 0)),
+            ("4k2P/8/8/8/8/8/8/4K3 w - - 0 1", FenError::PawnOnBackRank(8)),
+            ("4k3/8/8/8/8/8/8/p3K3 w - - 0 1", FenError::PawnOnBackRank(1)),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1", FenError::BadSideToMove("x".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkz - 0 1", FenError::BadCastling("KQkz".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KK - 0 1", FenError::BadCastling("KK".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1", FenError::BadEnPassant("e3".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq j6 0 1", FenError::BadEnPassant("j6".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1", FenError::BadCounter("x".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 -1", FenError::BadCounter("-1".to_string())),
+        ];
+
+        for &(fen, ref err) in cases.iter() {
+            assert_eq!(Board::try_from_fen(fen).err().as_ref(), Some(err), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn epd_fields() {
+        let board = Board::try_from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6").unwrap();
+        assert_eq!(board.castling, W_OO | B_OOO);
+        assert_eq!(board.en_passant, 3);
+        assert_eq!(board.halfmove_counter, 0);
+        assert_eq!(board.fullmove_counter, 0);
+    }
+
+    #[test]
+    fn long_games() {
+        let board = Board::try_from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 300").unwrap();
+        assert_eq!(board.fullmove_counter, 299);
+        assert!(board.to_fen().ends_with(" 0 300"));
+
+        let board = Board::try_from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99999999999999999999 1").unwrap();
+        assert_eq!(board.halfmove_counter, u16::MAX);
+    }
+}
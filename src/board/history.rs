@@ -5,8 +5,9 @@ use moves::types::{Move};
 pub struct MoveContext {
     pub pending_move: Move,
     pub zhash: u64,
+    pub pawn_hash: u64,
     pub castling: u8,
     pub en_passant: File,
-    pub halfmove_counter: u8,
-    pub fullmove_counter: u8,
+    pub halfmove_counter: u16,
+    pub fullmove_counter: u16,
 }
@@ -1,3 +1,6 @@
+#![allow(unused_imports)]
+#![allow(clippy::manual_range_contains, clippy::needless_return)]
+
 use constants::*;
 use bitboard::BitBoard;
 use std::boxed::Box;
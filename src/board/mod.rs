@@ -1,33 +1,38 @@
 pub mod history;
 pub mod mailbox;
 pub mod utils;
+pub mod fen;
+pub mod draws;
 
 use constants::*;
 use bitboard::BitBoard;
 use std::boxed::Box;
-use regex::Regex;
 use zobrist;
 use util::*;
 use types::*;
 use board::history::MoveContext;
 use board::mailbox::{Mailbox};
-use moves::types::{Move};
+use board::fen::{FenError, try_parse_fen_pieces};
 
 pub struct Board {
     pub bb: BitBoard,
     pub mb: Mailbox,
     pub to_move: Color,
     pub zhash: u64,
+
+    // the part of `zhash` that comes from the pawns
+    pub pawn_hash: u64,
+
     pub castling: u8,
     pub en_passant: File,
     pub history: Vec<MoveContext>,
 
     // Gets set to zero any time there's a non-reversible move or capture.  Increments every time
     // a player moves.  If hits fifty, the game is drawn.
-    pub halfmove_counter: u8,
+    pub halfmove_counter: u16,
 
     // Starts at 1.  Increments every time black moves.
-    pub fullmove_counter: u8,
+    pub fullmove_counter: u16,
 }
 
 impl PartialEq for Board {
@@ -45,9 +50,10 @@ impl Clone for Board {
    fn clone(&self) -> Board {
         Board {
             bb: self.bb,
-            mb: self.mb.clone(),
+            mb: self.mb,
             to_move: self.to_move,
             zhash: self.zhash,
+            pawn_hash: self.pawn_hash,
             castling: self.castling,
             en_passant: self.en_passant,
             history: self.history.clone(),
@@ -57,29 +63,34 @@ impl Clone for Board {
     } 
 }
 
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Board {
     pub fn new() -> Board {
         let mb = Mailbox::empty();
 
         let bb = BitBoard::create_from(&mb);
 
-        let board = Board { 
-            bb: bb,
-            mb: mb,
+        Board { 
+            bb,
+            mb,
             to_move: WHITE,
             zhash: 0,
+            pawn_hash: 0,
             castling: CASTLING_DEFAULT,
             en_passant: NO_EN_PASSANT,
             halfmove_counter: 0,
             fullmove_counter: 1,
             history: vec![],
-        };
-
-        board
+        }
     }
     
     pub fn starting_position() -> Board {
-        Board::new()
+        Board::from_fen(START_FEN)
     }
     
     pub fn whites_turn(&self) -> bool {
@@ -124,7 +135,7 @@ impl Board {
                 s.push_str(&format!("{} ", &piece_str));
             }
 
-            s.push_str("\n");
+            s.push('\n');
 
             lines.push(s);
         }
@@ -141,7 +152,7 @@ impl Board {
 
     pub fn print_board(&self) {
         let mut s: String = String::new();
-        s.push_str("\n");
+        s.push('\n');
         s.push_str("   A B C D E F G H\n");
         s.push_str("                  \n");
         
@@ -159,7 +170,7 @@ impl Board {
                 s.push_str(&format!("{} ", &piece_str));
             }
 
-            s.push_str("\n");
+            s.push('\n');
             //println!("{}", s);
         }
 
@@ -181,7 +192,7 @@ impl Board {
     // 3   - - - - - - - -
     // 2   - - - - - - - -
     // 1   - - - - - - - -
-    pub fn print_board_with_positions(&self, positions: &Vec<Position>) {
+    pub fn print_board_with_positions(&self, positions: &[Position]) {
 
         let mut s: String = String::new();
         s.push_str("   A B C D E F G H\n");
@@ -192,22 +203,20 @@ impl Board {
             
             for f in files_asc() {
                 let piece_type = self.mb.get(f, r);
-                let is_target = positions.iter().any(|&p| (p) == Position(f,r));
-                let mut piece_str: String;
-
-                if is_target {
-                    piece_str = match piece_type {
+                let is_target = positions.contains(&Position(f,r));
+                let piece_str: String = if is_target {
+                    match piece_type {
                         NO_PIECE => { "-".to_string() },
                         _ => { format!("{}", piece_type_to_char(piece_type)) }
-                    };
+                    }
                 } else {
-                  piece_str = "X".to_string();
+                    "X".to_string()
                 };
                     
                 s.push_str(&format!("{} ", &piece_str));
             }
 
-            s.push_str("\n");
+            s.push('\n');
             //println!("{}", s);
         }
 
@@ -217,6 +226,10 @@ impl Board {
     pub fn to_hash(&self) -> u64 {
         zobrist::get_board_hash(&self.get_pieces(), self.to_move, self.castling, self.en_passant)
     }
+
+    pub fn to_pawn_hash(&self) -> u64 {
+        zobrist::get_pawn_hash(&self.get_pieces())
+    }
     
     pub fn get_piece_position(&self, file: File, rank: Rank) -> PiecePosition {
        PiecePosition(self.mb.get(file, rank), file, rank)
@@ -246,7 +259,7 @@ impl Board {
                     }
 
                     consecutive_empty = 0;
-                    s.push_str(&piece_type_to_char(piece).to_string());
+                    s.push(piece_type_to_char(piece));
                 }
             }
 
@@ -255,125 +268,108 @@ impl Board {
             }
 
             if rank != 0 {
-                s.push_str("/");
+                s.push('/');
             }
         }
         
-        s.push_str(" ");
+        s.push(' ');
 
-        let turn = match(self.to_move) {
+        let turn = match self.to_move {
             WHITE => "w",
             _ => "b",
         };
 
-        s.push_str(&format!("{}", turn));
-        s.push_str(" ");
+        s.push_str(turn);
+        s.push(' ');
         
         if self.castling > 0 {
-            if self.castling | W_OO > 0 {
-                s.push_str("K");
+            if self.castling & W_OO > 0 {
+                s.push('K');
             }
             
-            if self.castling | W_OOO > 0 {
-                s.push_str("Q");
+            if self.castling & W_OOO > 0 {
+                s.push('Q');
             }
             
-            if self.castling | B_OO > 0 {
-                s.push_str("k");
+            if self.castling & B_OO > 0 {
+                s.push('k');
             }
             
-            if self.castling | B_OOO > 0 {
-                s.push_str("q");
+            if self.castling & B_OOO > 0 {
+                s.push('q');
             }
         } else {
-            s.push_str("-");
+            s.push('-');
         }
 
             
-        s.push_str(" ");
+        s.push(' ');
 
         if self.en_passant == NO_EN_PASSANT {
-            s.push_str(&"-");
+            s.push('-');
         } else {
-            let rank = match(self.to_move) {
-                WHITE => "3".to_string(),
-                _ => format!("{}", RANK_COUNT - 2),
+            // the square behind the pawn that just double pushed
+            let rank = match self.to_move {
+                WHITE => RANK_COUNT - 2,
+                _ => 3,
             };
 
-            s.push_str(&format!("{}{}", rank, file_to_char(self.en_passant)));
+            s.push_str(&format!("{}{}", file_to_char(self.en_passant), rank));
         }
 
-        s.push_str(" ");
+        s.push(' ');
         s.push_str(&format!("{}", self.halfmove_counter));
         
-        s.push_str(" ");
+        s.push(' ');
         s.push_str(&format!("{}", self.fullmove_counter + 1));
 
-        println!("s: {}", s);
         s
     }
 
     pub fn from_fen(fen: &str) -> Self {
-        let mut board = Board::new();
+        match Board::try_from_fen(fen) {
+            Ok(board) => board,
+            Err(err) => panic!("invalid fen '{}': {}", fen, err),
+        }
+    }
 
+    // Accepts full six field FENs and four field EPDs, where the move counters default to 0 and 1.
+    pub fn try_from_fen(fen: &str) -> Result<Self, FenError> {
         let groups = fen.split_whitespace().collect::<Vec<&str>>();
-        assert_eq!(groups.len(), 6);
-        
-        let pieces: PieceList = parse_fen_pieces(groups[0]);
+        if groups.len() != 4 && groups.len() != 6 {
+            return Err(FenError::WrongFieldCount(groups.len()));
+        }
+
+        let mut board = Board::new();
+
+        let pieces = try_parse_fen_pieces(groups[0])?;
+        fen::check_pieces(&pieces)?;
 
         for piece_position in &pieces {
            board.mb.set(piece_position.1, piece_position.2, piece_position.0);
         }
 
-        let turn: &str = groups[1];
-        assert_eq!(turn.len(), 1);
-        
-        if turn.starts_with("w") {
-            board.to_move = WHITE;
-        } else if turn.starts_with("b") {
-            board.to_move = BLACK;
-        } else {
-            assert!(false);
-        }
- 
-        let castling_s: &str = groups[2];
-     
-        board.castling = 0;
-        for c in castling_s.chars() {
-            let v = match c {
-                'K' => W_OO,
-                'Q' => W_OOO,
-                'k' => B_OO,
-                'q' => B_OOO,
-                _ => 0
-            };
-
-            board.castling = board.castling | v
-        }
+        board.to_move = fen::parse_side_to_move(groups[1])?;
+        board.castling = fen::parse_castling(groups[2])?;
+        board.en_passant = fen::parse_en_passant(groups[3], board.to_move)?;
 
-        let en_passant_str = groups[3];
-        let number_match = Regex::new(r"[1-8]").unwrap();
+        if groups.len() == 6 {
+            board.halfmove_counter = fen::parse_counter(groups[4])?;
 
-        if number_match.is_match(en_passant_str) {
-            let file_char = &number_match.replace(en_passant_str, "").into_owned();
-            let ep_file: File = char_to_file(file_char);
-            board.en_passant = ep_file;
+            //fullmove counter in fen is an ordinal so it starts at 1
+            board.fullmove_counter = fen::parse_counter(groups[5])?.saturating_sub(1);
         } else {
-            board.en_passant = NO_EN_PASSANT;
+            board.halfmove_counter = 0;
+            board.fullmove_counter = 0;
         }
 
-        board.halfmove_counter = groups[4].parse::<u8>().ok().unwrap_or(0);
-        
-        //fullmove counter in fen is an ordinal so it starts at 1
-        board.fullmove_counter = groups[5].parse::<u8>().ok().unwrap_or(1) - 1;
-        
         board.normalize();
 
-        board
+        Ok(board)
     }
     
-    pub fn get_pieces_iter(&self) -> Box<Iterator<Item=PiecePosition>> {
-        let r: Box<Iterator<Item=PiecePosition>> = Box::new(self.get_pieces().into_iter());
+    pub fn get_pieces_iter(&self) -> Box<dyn Iterator<Item=PiecePosition>> {
+        let r: Box<dyn Iterator<Item=PiecePosition>> = Box::new(self.get_pieces().into_iter());
 
         r
     }
@@ -415,8 +411,9 @@ impl Board {
     }
     
     pub fn normalize(&mut self) {
-        //self.bb = BitBoard::create_from(&self.mb);
+        self.bb = BitBoard::create_from(&self.mb);
         self.zhash = self.to_hash();
+        self.pawn_hash = self.to_pawn_hash();
     }
     
     // TODO: needs to be tested
@@ -427,36 +424,18 @@ impl Board {
         self.halfmove_counter = context.halfmove_counter;
         self.fullmove_counter = context.fullmove_counter;
         self.zhash = context.zhash;
+        self.pawn_hash = context.pawn_hash;
     }
 }
 
- 
-fn parse_fen_pieces(piece_str: &str) -> PieceList {
-    let ranks = piece_str.split('/').collect::<Vec<&str>>();
-
-    let mut pieces: PieceList = vec![];
-
-    for (rank_pos, rank_str) in ranks.into_iter().enumerate() {
-        let r: Rank = (7 - rank_pos as Rank) as Rank;
-        let mut f: File = 0;
-            
-        for c in rank_str.chars() {
-            //println!("char at {}: {}", f, c);
-            if c.is_digit(10) {
-                let digit: File = c.to_digit(10).expect("invalid number in fen piece list") as File;
-                f = f + digit;
-            } else if c.is_alphanumeric() {
-                let piece_position = PiecePosition(char_to_piece_type(&c), f as File, r as Rank);
-                pieces.push(piece_position);
-                f = f + 1;
-            } else {
-                println!("Invalid character in piece string: {}", c);
-            }
-        }
 
+// only the tests still use it, everything else goes through `try_parse_fen_pieces`
+#[allow(dead_code)]
+fn parse_fen_pieces(piece_str: &str) -> PieceList {
+    match try_parse_fen_pieces(piece_str) {
+        Ok(pieces) => pieces,
+        Err(err) => panic!("invalid fen pieces '{}': {}", piece_str, err),
     }
-
-    pieces
 }
 
 mod tests {
@@ -1,3 +1,6 @@
+#![allow(unused_imports)]
+#![allow(clippy::needless_late_init, clippy::redundant_field_names)]
+
 use super::{Board};
 use constants::{Rank, File, PieceType, FILE_COUNT, RANK_COUNT, BLACK, WHITE};
 use util::{piece_list_to_string};
@@ -1,3 +1,4 @@
+#![allow(clippy::redundant_static_lifetimes)]
 
 pub type Color = u8;
 pub const WHITE: Color = 0x00;
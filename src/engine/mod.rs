@@ -1,3 +1,3 @@
-use uci;
-use search_engine;
-
+pub mod uci;
+pub mod search_engine;
+pub mod table;
@@ -1,87 +1,427 @@
-use uci::timer::{UciTimer};
+#![allow(clippy::redundant_field_names)]
 
-pub struct EngineSettings { }
-impl EngineSettings {
-    pub fn default() -> EngineSettings {
-        EngineSettings {
+use board::Board;
+use moves::types::{Move};
+use moves::make_move::{make_move};
+use moves::generation::{generate_all_moves_for_color};
+use search::basic::{ab_search_observed};
+use search::observers::{NodeCounter};
+use search::types::{SearchParams, SearchHit, Score, mate_in_moves};
+use search::heuristics::Heuristics;
+use eval::{Evaluator, StandardEvaluator};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use engine::table::Table;
+use engine::uci::types::{Params};
+use engine::uci::timer::{UciTimer, parse};
+
+#[derive(Copy, Clone)]
+pub struct EngineSettings {
+    pub table_size_mb: usize,
+
+    // how many plies to search when the gui doesn't send `go depth`
+    pub search_depth: u8,
+}
 
+impl Default for EngineSettings {
+    fn default() -> Self {
+        EngineSettings {
+            table_size_mb: 128,
+            search_depth: 4,
         }
     }
 }
 
-
-
 pub struct SearchEngine {
     pub orig_board: Board,
     pub timer: UciTimer,
     settings: EngineSettings,
-    //table: Table,
+    table: Arc<Mutex<Table>>,
     //killers: Vec<Killer>,
     //rep: Vec<Hash>,
     ply: usize,
     node_count: usize,
+
+    // one entry per completed iteration of the last search
+    pub iterations: Vec<Iteration>,
     //irreversible: usize
 }
 
 impl SearchEngine {
     /// Create a new searcher from the start position
-    pub fn new(settings: EngineSettings, timer: Timer) -> Self {
+    pub fn new(settings: EngineSettings, timer: UciTimer) -> Self {
         let start = Board::starting_position();
 
         SearchEngine {
             orig_board: start,
             timer: timer,
             settings: settings,
-            //table: Table::empty(settings.table_size),
+            table: Arc::new(Mutex::new(Table::empty_mb(settings.table_size_mb))),
             //killers: vec![Killer::EMPTY],
             //rep: vec![start.hash],
             ply: 0,
             node_count: 0,
+            iterations: vec![],
             //irreversible: 0
         }
     }
-    
 
-    pub fn uci_update_settings(params: &Params) {
+    // Handles `setoption name <id> [value <x>]`
+    pub fn uci_update_settings(&mut self, params: &mut Params) {
+        if params.next() != Some("name") {
+            return;
+        }
 
-        while let Some(_) = params.find(|&word| word == "name") {
-            let setting: &str = &params.next().unwrap().to_lowercase();
+        let setting: String = params.by_ref()
+            .take_while(|&word| word != "value")
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .to_lowercase();
 
-            match setting {
-                // "hash" => {
-                //     let size_mb = parse(params.nth(1));
-                //     self.table = Table::empty_mb(size_mb);
-                //     self.settings.table_size = self.table.size();
-                // },
-                _ => ()
-            }
+        if setting == "hash" {
+            self.settings.table_size_mb = parse(params.next());
+            self.table = Arc::new(Mutex::new(Table::empty_mb(self.settings.table_size_mb)));
         }
     }
 
     // drop any caching and reset the board
-    pub fn reset() {
+    pub fn reset(&mut self) {
+        self.orig_board = Board::starting_position();
+        self.table.lock().unwrap().clear();
+        self.ply = 0;
+        self.node_count = 0;
+    }
 
+    // Is used to update the searcher with the position that the UI prefers.
+    //
+    //   position startpos moves e2e4 e7e5
+    //   position fen <fen> moves e2e4 e7e5
+    pub fn position(&mut self, params: &mut Params) {
+        let mut board = match params.next() {
+            Some("fen") => {
+                let fen = params.by_ref()
+                    .take_while(|&word| word != "moves")
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+
+                match Board::try_from_fen(&fen) {
+                    Ok(board) => board,
+                    Err(err) => {
+                        println!("info string invalid fen: {}", err);
+                        return;
+                    }
+                }
+            },
+            _ => {
+                // skip over the `moves` keyword, if there is one
+                params.next();
+                Board::starting_position()
+            }
+        };
+
+        // a move that isn't legal here leaves the position as it was after the last one that was
+        for mv_str in params {
+            let legal_moves = generate_all_moves_for_color(&board, board.to_move);
+
+            match legal_moves.into_iter().find(|mv| mv.to_pcn_string() == mv_str) {
+                Some(mv) => make_move(&mut board, mv),
+                None => {
+                    println!("info string illegal move: {}", mv_str);
+                    break;
+                }
+            }
+        }
+
+        self.orig_board = board;
+        self.ply = 0;
+        self.node_count = 0;
     }
-    
-    // Is used to update the searcher with the position that the UI prefers.  
-    pub fn position(to_fen: Option<String>, moves: &Vec<Move>) {
-        self.orig_board = match (to_fen) {
-            None       => Board::start_position(),
-            _fen       => Board::from_fen(fen),
+
+    // Searches depth 1, 2, 3... until the depth limit or the timer says there isn't time for
+    // another iteration.  Returns the result of the last iteration that finished; an iteration
+    // cut short by `stop` or the deadline is thrown away.  Depth 1 is always searched to the end,
+    // whatever the timer says, so that there's a move to play.
+    pub fn iterative_deepening(&mut self) -> Option<SearchHit> {
+        let to_move = self.orig_board.to_move;
+        let max_depth = match self.timer.depth_limit() {
+            Some(depth) => depth.max(1),
+            None if self.timer.is_timed() || self.timer.is_infinite() => MAX_SEARCH_DEPTH,
+            None => self.settings.search_depth.max(1),
         };
 
+        let mut board = self.orig_board.clone();
+        let mut best: Option<SearchHit> = None;
+        self.iterations.clear();
         self.node_count = 0;
-        // self.rep = vec![self.root.hash];
-        // self.killers = vec![];
-    
-        // // Remove half move, full move, and other words until there are moves
-        // for mv_str in params.skip_while(|&val| val != "moves").skip(1) {
-        //     let mv = self.root.move_from_str(mv_str);
-        //     if self.root.is_irreversible(mv) {
-        //         self.irreversible = self.root.ply + 1;
-        //     }
-        //     self.root.make_move(mv);
-        //     self.rep.push(self.root.hash);
-        // }
-    };
+
+        // entries from the last search are still good for move ordering, but shouldn't keep a
+        // slot from this one
+        self.table.lock().unwrap().set_ancient();
+
+        // killers and history from shallower iterations order the deeper ones, and the pawn
+        // structures the evaluator has cached stay good for all of them
+        let heuristics = Rc::new(RefCell::new(Heuristics::new()));
+        let evaluator: Rc<dyn Evaluator> = Rc::new(StandardEvaluator::new());
+
+        let mut depth: u8 = 1;
+        while depth <= max_depth && (depth == 1 || self.timer.should_search(depth as usize)) {
+            let timed = depth > 1;
+
+            // the search counts the leaf evaluation as a ply of its own
+            let search_params = SearchParams {
+                should_stop: if timed { Some(self.timer.should_stop.clone()) } else { None },
+                deadline: if timed { self.timer.deadline() } else { None },
+                table: Some(self.table.clone()),
+                heuristics: heuristics.clone(),
+                evaluator: evaluator.clone(),
+                ..SearchParams::new(depth + 1, to_move)
+            };
+
+            let nodes = Rc::new(Cell::new(0));
+            let observer = NodeCounter { nodes: nodes.clone() };
+            let hit = ab_search_observed(&mut board, &search_params, &observer);
+
+            if search_params.is_stopped() {
+                break;
+            }
+
+            self.node_count += nodes.get();
+            self.timer.toc(nodes.get());
+
+            let hit = match hit {
+                Some(hit) => hit,
+                None => break,
+            };
+
+            let iteration = Iteration {
+                depth,
+                nodes: nodes.get(),
+                time: self.timer.elapsed(),
+                score: hit.score_by_color(to_move),
+                pv: self.principal_variation(&hit),
+            };
+
+            println!("{}", iteration.info_line());
+            self.iterations.push(iteration);
+            best = Some(hit);
+
+            depth += 1;
+        }
+
+        best
+    }
+
+    // The move list of a search hit stops early when the line was cut off by a table hit, so the
+    // rest of it is read back out of the table.
+    fn principal_variation(&self, hit: &SearchHit) -> Vec<Move> {
+        let mut board = self.orig_board.clone();
+        for mv in &hit.move_list {
+            make_move(&mut board, *mv);
+        }
+
+        let mut pv = hit.move_list.clone();
+        pv.extend(self.table.lock().unwrap().pv(&board));
+        pv
+    }
+
+    // Searches the current position and reports the result to the gui.
+    pub fn go(&mut self) {
+        let to_move = self.orig_board.to_move;
+        self.timer.start(to_move);
+
+        let legal_moves = generate_all_moves_for_color(&self.orig_board, to_move);
+        let best_move = if legal_moves.is_empty() {
+            Move::NULL
+        } else {
+            self.iterative_deepening()
+                .and_then(|hit| hit.move_list.first().cloned())
+                .unwrap_or(Move::NULL)
+        };
+
+        // under `go infinite` the gui decides when the search is over, even if it ran out of
+        // depth first
+        while self.timer.is_infinite() && !self.timer.should_stop.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        println!("bestmove {}", best_move.to_pcn_string());
+    }
+}
+
+// deep enough that the clock always runs out first
+const MAX_SEARCH_DEPTH: u8 = 64;
+
+// What one iteration of the deepening found and what it cost.  Time is since the start of the
+// search, in seconds.
+pub struct Iteration {
+    pub depth: u8,
+    pub nodes: usize,
+    pub time: f64,
+    pub score: Score,
+    pub pv: Vec<Move>,
+}
+
+impl Iteration {
+    pub fn info_line(&self) -> String {
+        let pv = self.pv.iter()
+            .map(|mv| mv.to_pcn_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        format!("info depth {} score {} nodes {} time {} pv {}",
+            self.depth, uci_score(self.score), self.nodes, (self.time * 1000.0) as u64, pv)
+    }
+}
+
+fn uci_score(score: Score) -> String {
+    match mate_in_moves(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use constants::*;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+
+    #[allow(dead_code)]
+    fn engine() -> SearchEngine {
+        let timer = UciTimer::default(Arc::new(AtomicBool::new(false)));
+        SearchEngine::new(EngineSettings { table_size_mb: 1, ..EngineSettings::default() }, timer)
+    }
+
+    #[test]
+    fn position_startpos_with_moves() {
+        let mut engine = engine();
+        engine.position(&mut "startpos moves e2e4 e7e5 g1f3".split_whitespace());
+
+        let board = &engine.orig_board;
+        assert_eq!(board.to_move, BLACK);
+        assert_eq!(board.mb.get(4, 3), W_PAWN);
+        assert_eq!(board.mb.get(4, 4), B_PAWN);
+        assert_eq!(board.mb.get(5, 2), W_KNIGHT);
+        assert_eq!(board.mb.get(6, 0), NO_PIECE);
+    }
+
+    #[test]
+    fn position_fen_with_castling() {
+        let mut engine = engine();
+        engine.position(&mut "fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 moves e1g1 e8c8".split_whitespace());
+
+        let board = &engine.orig_board;
+        assert_eq!(board.mb.get(6, 0), W_KING);
+        assert_eq!(board.mb.get(5, 0), W_ROOK);
+        assert_eq!(board.mb.get(2, 7), B_KING);
+        assert_eq!(board.mb.get(3, 7), B_ROOK);
+        assert_eq!(board.castling, 0);
+    }
+
+    #[test]
+    fn position_bad_fen_keeps_board() {
+        let mut engine = engine();
+        engine.position(&mut "startpos moves e2e4".split_whitespace());
+        engine.position(&mut "fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1".split_whitespace());
+        assert_eq!(engine.orig_board.to_move, BLACK);
+
+        // no kings at all
+        engine.position(&mut "fen 8/8/8/8/8/8/8/8 w - - 0 1".split_whitespace());
+        assert_eq!(engine.orig_board.to_move, BLACK);
+    }
+
+    #[test]
+    fn position_stops_at_a_bad_move() {
+        let mut engine = engine();
+        engine.position(&mut "startpos moves e2e4 e7e6 e4e6 d2d4".split_whitespace());
+
+        let board = &engine.orig_board;
+        assert_eq!(board.to_move, WHITE);
+        assert_eq!(board.mb.get(4, 3), W_PAWN);
+        assert_eq!(board.mb.get(4, 5), B_PAWN);
+        assert_eq!(board.mb.get(3, 1), W_PAWN);
+
+        // not a move at all
+        engine.position(&mut "startpos moves e2e4 zz9 e7e5".split_whitespace());
+        assert_eq!(engine.orig_board.to_move, BLACK);
+        assert_eq!(engine.orig_board.mb.get(4, 6), B_PAWN);
+    }
+
+    #[test]
+    fn deepens_one_ply_at_a_time() {
+        let mut engine = engine();
+        engine.position(&mut "fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".split_whitespace());
+        engine.timer.replace(&mut "depth 3".split_whitespace());
+        engine.timer.start(WHITE);
+
+        let hit = engine.iterative_deepening().unwrap();
+        assert_eq!(hit.move_list[0].to_pcn_string(), "a1a8");
+
+        let depths = engine.iterations.iter().map(|it| it.depth).collect::<Vec<u8>>();
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert!(engine.iterations.iter().all(|it| it.nodes > 0));
+        assert!(engine.iterations[2].nodes > engine.iterations[0].nodes);
+    }
+
+    #[test]
+    fn infinite_searches_until_stopped() {
+        let mut engine = engine();
+        engine.position(&mut "fen 4k3/8/8/8/8/8/8/4K2R w - - 0 1".split_whitespace());
+        engine.timer.replace(&mut "infinite".split_whitespace());
+
+        // as main_loop does, so that building the slider tables doesn't come out of the search's time
+        ::magics::init();
+
+        let should_stop = engine.timer.should_stop.clone();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(500));
+            should_stop.store(true, Ordering::Relaxed);
+        });
+
+        let started = ::std::time::Instant::now();
+        engine.go();
+        stopper.join().unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(500));
+        assert!(engine.iterations.len() > engine.settings.search_depth as usize, "{}", engine.iterations.len());
+    }
+
+    #[test]
+    fn mates_are_reported_in_moves() {
+        use search::types::{mate_in, mated_in};
+
+        assert_eq!(uci_score(300), "cp 300");
+        assert_eq!(uci_score(mate_in(3)), "mate 2");
+        assert_eq!(uci_score(mated_in(4)), "mate -2");
+    }
+
+    #[test]
+    fn stop_before_first_iteration() {
+        // depth 1 still finishes, so the move comes from a search rather than the move list
+        let mut engine = engine();
+        engine.position(&mut "fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".split_whitespace());
+        engine.timer.replace(&mut "infinite".split_whitespace());
+        engine.timer.start(WHITE);
+        engine.timer.should_stop.store(true, ::std::sync::atomic::Ordering::Relaxed);
+
+        let hit = engine.iterative_deepening().unwrap();
+        assert_eq!(hit.move_list[0].to_pcn_string(), "a1a8");
+
+        let depths = engine.iterations.iter().map(|it| it.depth).collect::<Vec<u8>>();
+        assert_eq!(depths, vec![1]);
+    }
+
+    #[test]
+    fn setoption_hash() {
+        let mut engine = engine();
+        engine.uci_update_settings(&mut "name Hash value 32".split_whitespace());
+        assert_eq!(engine.settings.table_size_mb, 32);
+        assert_eq!(engine.table.lock().unwrap().size(), 32 * 1024 * 1024 / ::std::mem::size_of::<::engine::table::Entry>());
+    }
 }
@@ -1,88 +1,30 @@
-use rand::{Rng, SeedableRng, StdRng};
 use std::collections::HashSet;
 use std::mem;
-use types::*;
-use _move::Move;
-use board::*;
-use util::lsb;
 
-static mut piece_keys: [u64; 64*6*2] = [0; 64*6*2];
-static mut castle_keys: [u64; 16] = [0; 16];
-static mut ep_keys: [u64; 8] = [0; 8];
-static mut color_key: u64 = 0;
-
-fn set_random(arr: &mut [u64], rng: &mut StdRng) {
-    for elem in arr.iter_mut() {
-        *elem = rng.gen();
-    }
-}
-
-pub unsafe fn init() {
-    let seed: &[usize] = &[0];
-    let rng = &mut SeedableRng::from_seed(seed);
-    set_random(&mut piece_keys,  rng);
-    set_random(&mut castle_keys, rng);
-    set_random(&mut ep_keys,     rng);
-    color_key = rng.gen();
-}
-
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
-pub struct Hash { pub val: u64 }
-
-impl Hash {
-    pub fn init(sqs: &Squares, castling: u8, en_passant: u64, color: u8) -> Self {
-        let mut hash = Hash { val: 0 };
-
-        for (i, &sq) in sqs.iter().enumerate() {
-            hash.set_piece(i, sq);
-        }
-
-        hash.set_castling(castling);
-        hash.set_ep(en_passant);
-        if color == WHITE { hash.flip_color() }
-        hash
-    }
-
-    pub fn set_piece(&mut self, pos: usize, sq: u8) {
-        if sq != EMPTY {
-            let index = pos + ((sq & PIECE) >> 1) as usize * 64 + (sq & COLOR) as usize * 384;
-            self.val ^= unsafe { piece_keys[index] };
-        }
-    }
-
-    pub fn set_castling(&mut self, castling: u8) {
-        self.val ^= unsafe { castle_keys[castling as usize] };
-    }
-
-    pub fn set_ep(&mut self, en_passant: u64) {
-        if en_passant != 0 {
-            let file = lsb(en_passant) % 8;
-            self.val ^= unsafe { ep_keys[file as usize] };
-        }
-    }
-
-    pub fn flip_color(&mut self) {
-        self.val ^= unsafe { color_key };
-    }
-    
-    // ?
-    pub fn sub(&self) -> u16 {
-        (self.val >> 48) as u16
-    }
-}
+use board::Board;
+use moves::generation::generate_all_moves_for_color;
+use moves::make_move::make_move;
+use moves::types::Move;
+use search::types::{Score, score_to_table, score_from_table};
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Bound { Exact = 0, Lower = 1, Upper = 2 }
 
+// The table is keyed on `Board.zhash`: the low bits pick the slot and the top 16 bits are kept
+// in the entry to tell apart the positions that share it.
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct Entry {
-    pub score: i32,
+    pub score: Score,
     pub best_move: Move,
     pub hash16: u16,
     pub depth: u8,
     pub info: u8 // Upper 2 bits -> bound, lowest bit -> ancient
 }
 
+fn hash16(hash: u64) -> u16 {
+    (hash >> 48) as u16
+}
+
 impl Entry {
     const NULL: Entry = Entry { score: 0, best_move: Move::NULL, hash16: 0, depth: 0, info: 0 };
 
@@ -95,11 +37,15 @@ impl Entry {
     }
 
     pub fn bound(&self) -> Bound {
-        unsafe { mem::transmute(self.info >> 6) }
+        match self.info >> 6 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        }
     }
 
-    pub fn compare(&self, hash: &Hash) -> bool {
-        self.hash16 == hash.sub()
+    pub fn compare(&self, hash: u64) -> bool {
+        self.hash16 == hash16(hash)
     }
 }
 
@@ -109,67 +55,81 @@ pub struct Table {
 
 impl Table {
     pub fn empty(size: usize) -> Self {
-        Table { entries: vec![Entry::NULL; size] }
+        Table { entries: vec![Entry::NULL; size.max(1)] }
     }
 
     pub fn empty_mb(size_mb: usize) -> Self {
         Table::empty(size_mb * 1024 * 1024 / mem::size_of::<Entry>())
     }
 
-    pub fn probe(&self, hash: Hash, depth: u8, alpha: i32, beta: i32) -> (Option<i32>, Move) {
-        let entry = &self.entries[hash.val as usize % self.size()];
+    fn entry(&self, hash: u64) -> &Entry {
+        &self.entries[hash as usize % self.size()]
+    }
+
+    // Scores are from the point of view of the side to move, `depth` is the number of plies still
+    // to be searched below the position and `ply` how far it is from the root, which mate scores
+    // are counted from.  Returns a score when the entry is good enough to stand in for a search
+    // with the given window, and otherwise the move that was best last time.
+    pub fn probe(&self, hash: u64, depth: u8, ply: u8, alpha: Score, beta: Score) -> (Option<Score>, Move) {
+        let entry = self.entry(hash);
+
+        if !entry.is_empty() && entry.compare(hash) {
+            let score = score_from_table(entry.score, ply);
 
-        if !entry.is_empty() && entry.compare(&hash) {
-            // if entry.depth() == depth {
-            //     println!("{} {:?} d = {} a = {} b = {}", entry.score, entry.bound(), depth, alpha, beta);
-            // }
             if  entry.depth >= depth &&
                 match entry.bound() {
-                    Bound::Lower => entry.score >= beta,
-                    Bound::Upper => entry.score <= alpha,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
                     Bound::Exact => true }
-                { return (Some(entry.score), Move::NULL) }
+                { return (Some(score), entry.best_move) }
 
             return (None, entry.best_move)
         }
         (None, Move::NULL)
     }
 
-    pub fn best_move(&self, hash: Hash) -> Option<Move> {
-        let entry = &self.entries[hash.val as usize % self.size()];
+    pub fn best_move(&self, hash: u64) -> Option<Move> {
+        let entry = self.entry(hash);
 
-        if !entry.is_empty() && entry.compare(&hash) && entry.best_move != Move::NULL {
+        if !entry.is_empty() && entry.compare(hash) && entry.best_move != Move::NULL {
             return Some(entry.best_move)
         }
         None
     }
 
-    pub fn record(&mut self, board: &Board, score: i32, best_move: Move, depth: u8, bound: Bound) {
+    // Deeper searches win the slot, except over entries left behind by an earlier search.
+    pub fn record(&mut self, board: &Board, score: Score, best_move: Move, depth: u8, ply: u8, bound: Bound) {
         let size = self.size();
-        let entry = &mut self.entries[board.hash.val as usize % size];
+        let entry = &mut self.entries[board.zhash as usize % size];
 
         if entry.is_empty() || entry.depth <= depth || entry.ancient() {
             let info = (bound as u8) << 6;
-            *entry = Entry { score: score, best_move: best_move, hash16: board.hash.sub(),
-                depth: depth, info: info };
+            *entry = Entry { score: score_to_table(score, ply), best_move, hash16: hash16(board.zhash), depth, info };
         }
     }
 
+    // Follows the best moves from `board` for as long as they are legal and don't repeat a
+    // position.
     pub fn pv(&self, board: &Board) -> Vec<Move> {
         let mut pv = Vec::new();
         let mut visited = HashSet::new();
-        self.pv_cycle_track(*board, &mut pv, &mut visited);
+        self.pv_cycle_track(board.clone(), &mut pv, &mut visited);
         pv
     }
 
-    pub fn pv_cycle_track(&self, mut board: Board, pv: &mut Vec<Move>, visited: &mut HashSet<Hash>) {
-        let mv = self.best_move(board.hash);
+    pub fn pv_cycle_track(&self, mut board: Board, pv: &mut Vec<Move>, visited: &mut HashSet<u64>) {
+        let mv = self.best_move(board.zhash);
 
         if let Some(m) = mv {
+            // the slot may belong to a different position with the same top 16 bits
+            if !generate_all_moves_for_color(&board, board.to_move).contains(&m) {
+                return;
+            }
+
             pv.push(m);
-            board.make_move(m);
+            make_move(&mut board, m);
 
-            if visited.insert(board.hash) {
+            if visited.insert(board.zhash) {
                 self.pv_cycle_track(board, pv, visited);
             }
         }
@@ -179,6 +139,12 @@ impl Table {
         self.entries.len()
     }
 
+    pub fn clear(&mut self) {
+        for entry in &mut self.entries {
+            *entry = Entry::NULL;
+        }
+    }
+
     pub fn set_ancient(&mut self) -> usize {
         let mut num = 0;
         for entry in &mut self.entries {
@@ -191,3 +157,80 @@ impl Table {
     }
 }
 
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use constants::*;
+
+    #[test]
+    fn probe_respects_bounds_and_depth() {
+        let board = Board::from_fen(START_FEN);
+        let mv = Move::from_pcn_string("e2e4", &board);
+        let mut table = Table::empty(1024);
+
+        table.record(&board, 3, mv, 4, 0, Bound::Lower);
+
+        // a lower bound only cuts when it's at least beta
+        assert_eq!(table.probe(board.zhash, 4, 0, -10, 3), (Some(3), mv));
+        assert_eq!(table.probe(board.zhash, 4, 0, -10, 10), (None, mv));
+
+        // and only for searches no deeper than the one that stored it
+        assert_eq!(table.probe(board.zhash, 5, 0, -10, 3), (None, mv));
+
+        table.record(&board, 1, mv, 6, 0, Bound::Upper);
+        assert_eq!(table.probe(board.zhash, 5, 0, 1, 10), (Some(1), mv));
+        assert_eq!(table.probe(board.zhash, 5, 0, 0, 10), (None, mv));
+
+        assert_eq!(table.probe(board.zhash ^ (1 << 63), 0, 0, -10, 10), (None, Move::NULL));
+    }
+
+    #[test]
+    fn mates_are_stored_relative_to_the_position() {
+        use search::types::{mate_in, mated_in};
+
+        let board = Board::from_fen(START_FEN);
+        let mv = Move::from_pcn_string("e2e4", &board);
+        let mut table = Table::empty(1024);
+
+        // a mate 5 plies from the root, found at ply 2, is a mate in 3 from the position, which
+        // reached at ply 4 instead is a mate 7 plies from the root
+        table.record(&board, mate_in(5), mv, 3, 2, Bound::Exact);
+        assert_eq!(table.probe(board.zhash, 3, 4, -10, 10), (Some(mate_in(7)), mv));
+
+        table.record(&board, mated_in(6), mv, 3, 2, Bound::Exact);
+        assert_eq!(table.probe(board.zhash, 3, 0, -10, 10), (Some(mated_in(4)), mv));
+    }
+
+    #[test]
+    fn ancient_entries_are_replaced() {
+        let board = Board::from_fen(START_FEN);
+        let e4 = Move::from_pcn_string("e2e4", &board);
+        let d4 = Move::from_pcn_string("d2d4", &board);
+        let mut table = Table::empty(16);
+
+        table.record(&board, 0, e4, 6, 0, Bound::Exact);
+        table.record(&board, 0, d4, 2, 0, Bound::Exact);
+        assert_eq!(table.best_move(board.zhash), Some(e4));
+
+        assert_eq!(table.set_ancient(), 1);
+        table.record(&board, 0, d4, 2, 0, Bound::Exact);
+        assert_eq!(table.best_move(board.zhash), Some(d4));
+    }
+
+    #[test]
+    fn pv_follows_best_moves() {
+        let mut board = Board::from_fen(START_FEN);
+        let mut table = Table::empty(4096);
+
+        let start = board.clone();
+        for pcn in ["e2e4", "e7e5", "g1f3"].iter() {
+            let mv = Move::from_pcn_string(pcn, &board);
+            table.record(&board, 0, mv, 1, 0, Bound::Exact);
+            make_move(&mut board, mv);
+        }
+
+        let pv = table.pv(&start).iter().map(|mv| mv.to_pcn_string()).collect::<Vec<String>>();
+        assert_eq!(pv, vec!["e2e4", "e7e5", "g1f3"]);
+    }
+}
@@ -1,28 +1,38 @@
-mod timer;
+pub mod timer;
+pub mod types;
 
-use timer::{UciTimer, TimeSettings};
+use self::timer::{UciTimer};
+use self::types::{Params};
 
-//use std::io::prelude::*;
-use std::io::{stdin, BufReader};
-use std::fs::File;
+use std::io::{stdin, BufRead};
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use time;
 
-use type
+use board::Board;
+use magics;
+use moves::{divide};
+use engine::search_engine::{SearchEngine, EngineSettings};
+use self::timer::{parse};
 
-const ENGINE_NAME: &'static str = "Skeleton 0.0.1";
+const ENGINE_NAME: &str = "Skeleton 0.0.1";
+
+macro_rules! lock {
+    ($searcher:expr) => ($searcher.lock().unwrap())
+}
 
 pub fn main_loop() {
+    // before any `go`, so that building them doesn't eat into the first search's time
+    magics::init();
+
     let should_stop = Arc::new(AtomicBool::new(false));
-    let timer = Timer::default(should_stop.clone());
+    let timer = UciTimer::default(should_stop.clone());
 
-    let searcher = Arc::new(Mutex::new(Searcher::new(EngineSettings::default(), timer)));
+    let searcher = Arc::new(Mutex::new(SearchEngine::new(EngineSettings::default(), timer)));
 
     let stdin = stdin();
     for line in stdin.lock().lines() {
-        let line = line.unwrap_or("".into());
+        let line = line.unwrap_or_default();
         let mut params: Params = line.split_whitespace();
 
         if let Some(first_word) = params.next() {
@@ -31,24 +41,17 @@ pub fn main_loop() {
                 "isready"    => println!("readyok"),
                 "setoption"  => lock!(searcher).uci_update_settings(&mut params),
                 "ucinewgame" => lock!(searcher).reset(),
-                "position"   => lock!(searcher).unwind_to_position(&mut params),
-
-        // // Remove half move, full move, and other words until there are moves
-        // for mv_str in params.skip_while(|&val| val != "moves").skip(1) {
-        //     let mv = self.root.move_from_str(mv_str);
-        //     if self.root.is_irreversible(mv) {
-        //         self.irreversible = self.root.ply + 1;
-        //     }
-        //     self.root.make_move(mv);
-        //     self.rep.push(self.root.hash);
-        // }
+                "position"   => lock!(searcher).position(&mut params),
                 "stop"       => should_stop.store(true, Ordering::Relaxed),
                 "quit"       => return,
-                //"perft"      => perft(&lock!(searcher).root, &mut params),
-                //"test"       => run_test(&mut lock!(searcher), params.next()),
+                "perft"      => perft(&lock!(searcher).orig_board, &mut params),
                 "go"         => {
                     lock!(searcher).timer.replace(&mut params);
 
+                    // cleared here rather than in the search thread, so that a `stop` sent
+                    // straight after `go` isn't wiped out before the search sees it
+                    should_stop.store(false, Ordering::Relaxed);
+
                     let searcher = searcher.clone();
                     thread::spawn(move || {
                         lock!(searcher).go();
@@ -60,67 +63,25 @@ pub fn main_loop() {
     }
 }
 
-pub fn position(&mut params: Params) {
-    // Remove half move, full move, and other words until there are moves
-    for mv_str in params.skip_while(|&val| val != "moves").skip(1) {
-        //Move.
-        let mv = self.root.move_from_str(mv_str);
-        if self.root.is_irreversible(mv) {
-            self.irreversible = self.root.ply + 1;
-        }
-        self.root.make_move(mv);
-        self.rep.push(self.root.hash);
-    }
-}
+// `perft N` prints the node count below each root move, in the same format as stockfish's
+// `go perft N` so the two can be diffed.
+pub fn perft(board: &Board, params: &mut Params) {
+    let depth: u8 = parse(params.next());
+    let mut board = board.clone();
 
-#[derive(Copy, Clone)]
-pub struct EngineSettings {
-    pub table_size: usize
-}
+    if depth == 0 {
+        println!("\nNodes searched: 1\n");
+        return;
+    }
 
-impl Default for EngineSettings {
-    fn default() -> Self {
-        EngineSettings {
-            //table_size: 10_000_000
-        }
+    let by_move = divide(&mut board, depth);
+    for &(mv, nodes) in &by_move {
+        println!("{}: {}", mv.to_pcn_string(), nodes);
     }
-}
 
-// pub fn run_test(searcher: &mut Searcher, test: Option<&str>) {
-//     match test {
-//         Some("perf") => positions("testing/positions/performance",
-//                 searcher, &mut |s| s.go()),
-//         Some("move") => positions("testing/positions/perftsuite.epd",
-//                 searcher, &mut |s| println!("{}", s.root.perft(6, true))),
-//         _ => println!("Error: Valid options are `perf` or `move`")
-//     };
-// }
-
-// pub fn perft(board: &Board, params: &mut Params) {
-//     let depth = parse_or(params.next(), 5);
-// 
-//     println!("total = {}\n", board.perft(depth, true));
-// }
-
-// 
-// pub fn positions(path: &str, searcher: &mut Searcher, do_work: &mut FnMut(&mut Searcher)) {
-//     let file = match File::open(path) {
-//         Ok(file) => BufReader::new(file),
-//         Err(e)   => panic!("Test suite {} could not be read. {:?}", path, e)
-//     };
-// 
-//     let start = time::precise_time_s();
-//     searcher.timer.replace(&mut "wtime 10000 btime 10000 movestogo 1".split_whitespace());
-// 
-//     for line in file.lines().take(10) {
-//         let fen = String::from("fen ") + &line.unwrap();
-//         println!("{}", fen);
-// 
-//         searcher.position(&mut fen.split_whitespace());
-//         do_work(searcher);
-//     }
-//     println!("Time taken = {} seconds", time::precise_time_s() - start);
-// }
+    let total: u64 = by_move.iter().map(|&(_, nodes)| nodes).sum();
+    println!("\nNodes searched: {}\n", total);
+}
 
 pub fn uci() {
     println!("id name {}", ENGINE_NAME);
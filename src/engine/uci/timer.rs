@@ -1,23 +1,47 @@
+#![allow(clippy::redundant_field_names)]
+
 use std::sync::atomic::Ordering;
-use types::{Flag, Params, ClockTime, ClockIncTime};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use constants::{Color, WHITE, BLACK};
+use engine::uci::types::{Flag, Params, ClockTime, ClockIncTime};
+
+const I_WHITE: usize = WHITE as usize;
+const I_BLACK: usize = BLACK as usize;
+
+// Parses the next uci parameter, falling back to the default value when it's missing or garbled.
+pub fn parse<T: FromStr + Default>(param: Option<&str>) -> T {
+    param.and_then(|s| s.parse::<T>().ok()).unwrap_or_default()
+}
 
 pub struct TimeSettings {
     times_for: [ClockTime; 2],
     inc_for: [ClockIncTime; 2],
     moves_to_go: usize,
     ponder: bool,
-    infinite: bool
+    infinite: bool,
+
+    // `go depth N` limits the search to N plies regardless of the clock
+    depth: Option<u8>,
+
+    // `go movetime N`: exactly this long for the move, in milliseconds, whatever the clocks say
+    movetime: Option<ClockTime>,
+
+    // whether the gui sent any clock times; without them only the depth limits the search
+    timed: bool,
 }
 
 impl TimeSettings {
     pub fn parse_uci(mut self, params: &mut Params) -> Self {
         while let Some(option) = params.next() {
             match option {
-                "wtime" => self.times_for[I_WHITE] = parse(params.next()),
-                "btime" => self.times_for[I_BLACK] = parse(params.next()),
+                "wtime" => { self.times_for[I_WHITE] = parse(params.next()); self.timed = true },
+                "btime" => { self.times_for[I_BLACK] = parse(params.next()); self.timed = true },
                 "winc"  => self.inc_for[I_WHITE]   = parse(params.next()),
                 "binc"  => self.inc_for[I_BLACK]   = parse(params.next()),
                 "movestogo" => self.moves_to_go    = parse(params.next()),
+                "depth" => self.depth = Some(parse(params.next())),
+                "movetime" => self.movetime = Some(parse(params.next())),
                 "ponder"   => self.ponder = true,
                 "infinite" => self.infinite = true,
                 _ => ()
@@ -44,13 +68,19 @@ impl Default for TimeSettings {
             // increment times for white and black respectively
             // ?A re these the initial time amounts or the real-time times?
             inc_for: [0.0, 0.0],
-            
+
             // Moves until the next set of time controls
             moves_to_go: 40,
-            
+
             ponder: false,
 
-            infinite: false
+            infinite: false,
+
+            depth: None,
+
+            movetime: None,
+
+            timed: false,
         }
     }
 }
@@ -62,7 +92,7 @@ pub struct UciTimer {
     times: Vec<f64>,
     side: usize,
     safety: f64,
-    init: f64
+    init: Instant
 }
 
 impl UciTimer {
@@ -73,14 +103,14 @@ impl UciTimer {
             nodes: vec![0],
             times: vec![0.0],
 
-            // 
+            //
             side: !(I_WHITE | I_BLACK), // Initialize later
 
             // ?
             safety: 0.1,
 
             // ?
-            init: 0.0
+            init: Instant::now()
         }
     }
 
@@ -93,12 +123,13 @@ impl UciTimer {
         *self = UciTimer::new(self.should_stop.clone(), settings);
     }
 
-    pub fn start(&mut self, side: u8) {
-        self.init = time::precise_time_s();
+    pub fn start(&mut self, side: Color) {
+        self.init = Instant::now();
+        self.nodes = vec![0];
+        self.times = vec![0.0];
         self.side = side as usize;
-        self.should_stop.store(false, Ordering::Relaxed);
     }
-    
+
     // ?
     pub fn toc(&mut self, node_count: usize) {
         self.nodes.push(node_count);
@@ -107,20 +138,133 @@ impl UciTimer {
     }
 
     pub fn elapsed(&self) -> f64 {
-        time::precise_time_s() - self.init
+        let dt = self.init.elapsed();
+        dt.as_secs() as f64 + dt.subsec_nanos() as f64 * 1e-9
+    }
+
+    pub fn depth_limit(&self) -> Option<u8> {
+        self.settings.depth
+    }
+
+    // `go infinite`: search until `stop`, and don't answer before it comes
+    pub fn is_infinite(&self) -> bool {
+        self.settings.infinite
+    }
+
+    pub fn is_timed(&self) -> bool {
+        (self.settings.timed || self.settings.movetime.is_some()) && !self.settings.infinite
+    }
+
+    fn alloc_time(&self) -> f64 {
+        (1.0 - self.safety) * self.settings.time(self.side) / self.settings.moves_to_go as f64
+            + self.settings.inc(self.side)
+    }
+
+    // The point at which a search in progress should be abandoned.  Iterations normally stop
+    // well before this via `should_search`; it only catches one that runs far over its estimate.
+    // With a fixed move time it's simply when that time is up.
+    pub fn deadline(&self) -> Option<Instant> {
+        if !self.is_timed() {
+            return None;
+        }
+
+        if let Some(movetime) = self.settings.movetime {
+            return Some(self.init + Duration::from_secs_f64(movetime.max(0.0) / 1000.0));
+        }
+
+        let remaining = (1.0 - self.safety) * self.settings.time(self.side);
+        let hard_limit = (self.alloc_time() * 3.0).min(remaining / 2.0).max(0.0);
+        Some(self.init + Duration::from_secs_f64(hard_limit))
     }
 
     /// Return whether we should search to a given depth, or give the best move so far
     pub fn should_search(&self, depth: usize) -> bool {
-        if depth <= 2 { return true }
-        
-        let estimate = self.times[depth-1] * self.nodes[depth-1] as f64 / self.nodes[depth-2] as f64;
-        let alloc_time = (1.0 - self.safety) * self.settings.time(self.side) / self.settings.moves_to_go as f64
-                         + self.settings.inc(self.side);
-
-        !self.should_stop.load(Ordering::Relaxed) && (
-        self.settings.infinite ||
+        if self.should_stop.load(Ordering::Relaxed) { return false }
+        if depth <= 2 || !self.is_timed() { return true }
+
+        // the deadline stops an iteration that can't finish in time
+        if let Some(movetime) = self.settings.movetime {
+            return self.elapsed() < movetime / 1000.0;
+        }
+
+        let estimate = self.times[depth-1] * self.nodes[depth-1] as f64 / self.nodes[depth-2].max(1) as f64;
+        let alloc_time = self.alloc_time();
+
         alloc_time - self.times[depth-1] > estimate * 0.3 ||
-        alloc_time / 1.5 > self.elapsed())
+        alloc_time / 1.5 > self.elapsed()
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn parse_go_params() {
+        let mut params = "wtime 1000 btime 2000 winc 10 binc 20 movestogo 5 depth 6".split_whitespace();
+        let settings = TimeSettings::default().parse_uci(&mut params);
+
+        assert_eq!(settings.time(I_WHITE), 1.0);
+        assert_eq!(settings.time(I_BLACK), 2.0);
+        assert_eq!(settings.inc(I_BLACK), 0.02);
+        assert_eq!(settings.moves_to_go, 5);
+        assert_eq!(settings.depth, Some(6));
+        assert!(!settings.infinite);
+        assert!(settings.timed);
+    }
+
+    #[test]
+    fn depth_only_is_untimed() {
+        let flag = ::std::sync::Arc::new(::std::sync::atomic::AtomicBool::new(false));
+        let mut timer = UciTimer::default(flag);
+        timer.replace(&mut "depth 3".split_whitespace());
+        timer.start(WHITE);
+
+        assert!(!timer.is_timed());
+        assert_eq!(timer.deadline(), None);
+        assert!(timer.should_search(10));
+
+        timer.should_stop.store(true, Ordering::Relaxed);
+        assert!(!timer.should_search(1));
+    }
+
+    #[test]
+    fn start_keeps_a_pending_stop() {
+        // a `stop` can come in before the search thread gets going
+        let flag = ::std::sync::Arc::new(::std::sync::atomic::AtomicBool::new(false));
+        let mut timer = UciTimer::default(flag);
+        timer.replace(&mut "infinite".split_whitespace());
+        timer.should_stop.store(true, Ordering::Relaxed);
+        timer.start(WHITE);
+
+        assert!(!timer.should_search(1));
+    }
+
+    #[test]
+    fn infinite_ignores_the_clock() {
+        let flag = ::std::sync::Arc::new(::std::sync::atomic::AtomicBool::new(false));
+        let mut timer = UciTimer::default(flag);
+        timer.replace(&mut "wtime 1000 btime 1000 infinite".split_whitespace());
+        timer.start(WHITE);
+
+        assert!(timer.is_infinite());
+        assert!(!timer.is_timed());
+        assert_eq!(timer.deadline(), None);
+        assert!(timer.should_search(40));
+    }
+
+    #[test]
+    fn movetime_is_the_deadline() {
+        let flag = ::std::sync::Arc::new(::std::sync::atomic::AtomicBool::new(false));
+        let mut timer = UciTimer::default(flag);
+        timer.replace(&mut "movetime 250".split_whitespace());
+        timer.start(WHITE);
+
+        assert!(timer.is_timed());
+        assert_eq!(timer.deadline(), Some(timer.init + Duration::from_millis(250)));
+        assert!(timer.should_search(3));
+
+        timer.init -= Duration::from_millis(300);
+        assert!(!timer.should_search(3));
     }
 }
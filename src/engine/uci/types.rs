@@ -1,4 +1,5 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use std::str::{SplitWhitespace};
 
 pub type Flag = Arc<AtomicBool>;
@@ -0,0 +1,212 @@
+// How active the pieces are: how many safe squares they reach, the bishop pair, rooks on open
+// files and the seventh rank, and knights on outposts.
+
+use constants::*;
+use board::Board;
+use bitboard::{ALL_WHITE, ALL_BLACK, square_to_pos};
+use types::PiecePosition;
+use util::to_color;
+use moves::attacks::PAWN_ATTACKS;
+use moves::generation::get_piece_attack_set;
+
+use super::pawns::{file_mask, adjacent_files, ranks_ahead, relative_rank};
+
+// (midgame, endgame) per safe square, indexed by piece class.  Each class has a typical number
+// of squares it reaches, and only the difference from that counts, so that a piece with average
+// scope is neither a bonus nor a penalty.
+const MOBILITY_MG: [i32; 6] = [0, 4, 5, 2, 1, 0];
+const MOBILITY_EG: [i32; 6] = [0, 4, 5, 4, 2, 0];
+const MOBILITY_BASE: [i32; 6] = [0, 4, 6, 7, 13, 0];
+
+const BISHOP_PAIR: (i32, i32) = (30, 50);
+
+// no pawns at all on the rook's file, and none of our own
+const ROOK_OPEN_FILE: (i32, i32) = (25, 10);
+const ROOK_SEMI_OPEN_FILE: (i32, i32) = (12, 6);
+
+// only while there are pawns there to eat or the king is stuck behind it
+const ROOK_ON_SEVENTH: (i32, i32) = (20, 30);
+
+// A knight on the 4th to 6th rank, defended by a pawn, that no enemy pawn can ever chase away.
+const KNIGHT_OUTPOST: (i32, i32) = (20, 10);
+
+// every square a pawn of `color` attacks
+fn pawn_attacks(board: &Board, color: Color) -> u64 {
+    let mut pawns = board.bb[to_color(W_PAWN, color == WHITE)];
+    let mut attacks = 0;
+
+    while pawns != 0 {
+        attacks |= PAWN_ATTACKS[color as usize][pawns.trailing_zeros() as usize];
+        pawns &= pawns - 1;
+    }
+
+    attacks
+}
+
+// For `color`'s pieces, as (midgame, endgame).
+fn activity_for(board: &Board, color: Color) -> (i32, i32) {
+    let is_white = color == WHITE;
+    let enemy = color ^ 1;
+    let own_pieces = board.bb[if is_white { ALL_WHITE } else { ALL_BLACK }];
+    let own_pawns = board.bb[to_color(W_PAWN, is_white)];
+    let enemy_pawns = board.bb[to_color(W_PAWN, !is_white)];
+
+    // squares an enemy pawn covers aren't worth going to, and our own pieces are in the way
+    let unsafe_squares = pawn_attacks(board, enemy) | own_pieces;
+    let defended_by_pawns = pawn_attacks(board, color);
+
+    let (mut mg, mut eg) = (0, 0);
+
+    for class in KNIGHT..KING {
+        let piece = to_color(class, is_white);
+        let mut pieces = board.bb[piece];
+
+        while pieces != 0 {
+            let square = pieces.trailing_zeros() as usize;
+            pieces &= pieces - 1;
+
+            let pos = square_to_pos(square);
+            let attacks = get_piece_attack_set(PiecePosition(piece, pos.0, pos.1), board);
+            let safe = (attacks & !unsafe_squares).count_ones() as i32 - MOBILITY_BASE[class as usize];
+
+            mg += MOBILITY_MG[class as usize] * safe;
+            eg += MOBILITY_EG[class as usize] * safe;
+
+            let file = file_mask(square % 8);
+            let rank = relative_rank(color, square);
+
+            if class == ROOK && own_pawns & file == 0 {
+                let bonus = if enemy_pawns & file == 0 { ROOK_OPEN_FILE } else { ROOK_SEMI_OPEN_FILE };
+                mg += bonus.0;
+                eg += bonus.1;
+            }
+
+            if class == ROOK && rank == 6 {
+                let enemy_kings = board.bb[to_color(W_KING, !is_white)];
+                let seventh = 0xff << (8 * (square / 8));
+
+                // a board set up without one has no king to cut off
+                let king_on_eighth = enemy_kings != 0
+                    && relative_rank(color, enemy_kings.trailing_zeros() as usize) == 7;
+
+                if enemy_pawns & seventh != 0 || king_on_eighth {
+                    mg += ROOK_ON_SEVENTH.0;
+                    eg += ROOK_ON_SEVENTH.1;
+                }
+            }
+
+            // the enemy pawns that could one day attack the square are the ones on the files
+            // either side, further up the board
+            let chasers = enemy_pawns & adjacent_files(square % 8) & ranks_ahead(color, square / 8);
+
+            if class == KNIGHT && (3..6).contains(&rank) && defended_by_pawns & 1 << square != 0 && chasers == 0 {
+                mg += KNIGHT_OUTPOST.0;
+                eg += KNIGHT_OUTPOST.1;
+            }
+        }
+    }
+
+    if board.bb[to_color(W_BISHOP, is_white)].count_ones() >= 2 {
+        mg += BISHOP_PAIR.0;
+        eg += BISHOP_PAIR.1;
+    }
+
+    (mg, eg)
+}
+
+// As (midgame, endgame), from white's point of view.
+pub fn piece_activity(board: &Board) -> (i32, i32) {
+    let white = activity_for(board, WHITE);
+    let black = activity_for(board, BLACK);
+    (white.0 - black.0, white.1 - black.1)
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    fn activity(fen: &str) -> (i32, i32) {
+        activity_for(&Board::from_fen(fen), WHITE)
+    }
+
+    #[test]
+    fn mobility_counts_safe_squares() {
+        // a knight in the middle reaches eight squares, in the corner two
+        assert_eq!(activity("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").0, MOBILITY_MG[1] * (8 - MOBILITY_BASE[1]));
+        assert_eq!(activity("4k3/8/8/8/8/8/8/N3K3 w - - 0 1").0, MOBILITY_MG[1] * (2 - MOBILITY_BASE[1]));
+
+        // squares black's pawns cover don't count, and neither do ones our own pieces stand on
+        let covered = activity("4k3/8/2p1p3/8/3N4/8/8/4K3 w - - 0 1");
+        let blocked = activity("4k3/8/8/8/3N4/1P6/8/4K3 w - - 0 1");
+        assert_eq!(covered.0, MOBILITY_MG[1] * (6 - MOBILITY_BASE[1]));
+        assert_eq!(blocked.0, MOBILITY_MG[1] * (7 - MOBILITY_BASE[1]));
+
+        // a bishop hemmed in by its own pawns
+        let open = activity("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1");
+        let hemmed = activity("4k3/8/8/8/8/8/1P1P4/2B1K3 w - - 0 1");
+        assert!(open.0 > hemmed.0);
+    }
+
+    #[test]
+    fn bishop_pair() {
+        let pair = activity("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1");
+        let with_knight = activity("4k3/8/8/8/8/8/8/2B1KN2 w - - 0 1");
+        let bishop = activity("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1");
+        let second_bishop = activity("4k3/8/8/8/8/8/8/4KB2 w - - 0 1");
+
+        assert_eq!(pair.1 - bishop.1 - second_bishop.1, BISHOP_PAIR.1);
+        assert!(pair.1 > with_knight.1);
+    }
+
+    #[test]
+    fn rooks_on_files() {
+        // the rook on a2, with the a-file clear, with black's pawn on a7 taking a8 away from it, or
+        // with our own pawn on a3 taking away all six squares up the file
+        let open = activity("4k3/8/8/8/8/8/R7/4K3 w - - 0 1");
+        let semi_open = activity("4k3/p7/8/8/8/8/R7/4K3 w - - 0 1");
+        let closed = activity("4k3/8/8/8/8/P7/R7/4K3 w - - 0 1");
+
+        assert_eq!(open.0 - semi_open.0, ROOK_OPEN_FILE.0 - ROOK_SEMI_OPEN_FILE.0 + MOBILITY_MG[3]);
+        assert_eq!(open.0 - closed.0, ROOK_OPEN_FILE.0 + 6 * MOBILITY_MG[3]);
+    }
+
+    #[test]
+    fn rook_on_the_seventh() {
+        // the rook reaches fourteen squares from either rank
+        let seventh = activity("4k3/R7/8/8/8/8/8/4K3 w - - 0 1");
+        let sixth = activity("4k3/8/R7/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(seventh.1 - sixth.1, ROOK_ON_SEVENTH.1);
+
+        // nothing there to go after
+        let empty_seventh = activity("8/R7/8/4k3/8/8/8/4K3 w - - 0 1");
+        let empty_sixth = activity("8/8/R7/4k3/8/8/8/4K3 w - - 0 1");
+        assert_eq!(empty_seventh, empty_sixth);
+
+        // or no king at all
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/r7/4K3 b - - 0 1");
+        board.bb[W_KING] = 0;
+        let kingless = activity_for(&board, BLACK);
+        assert_eq!(kingless, activity_for(&Board::from_fen("4k3/8/8/4K3/8/8/r7/8 b - - 0 1"), BLACK));
+    }
+
+    #[test]
+    fn knight_outposts() {
+        // on d5, defended by the e4 pawn, with no black pawn on the c- or e-file left to chase it
+        let outpost = activity("4k3/8/8/3N4/4P3/8/8/4K3 w - - 0 1");
+        let chased = activity("4k3/2p5/8/3N4/4P3/8/8/4K3 w - - 0 1");
+        let undefended = activity("4k3/8/8/3N4/8/8/4P3/4K3 w - - 0 1");
+
+        assert_eq!(outpost.1 - undefended.1, KNIGHT_OUTPOST.1);
+        assert!(chased.1 < outpost.1);
+    }
+
+    #[test]
+    fn activity_is_symmetric() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let flipped = Board::from_fen("r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R w KQkq - 0 1");
+
+        assert_eq!(activity_for(&board, WHITE), activity_for(&flipped, BLACK));
+        assert_eq!(piece_activity(&board), (-piece_activity(&flipped).0, -piece_activity(&flipped).1));
+    }
+}
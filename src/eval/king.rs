@@ -0,0 +1,185 @@
+// King safety: the pawns in front of the king, enemy pawns storming towards it, open files next
+// to it, and enemy pieces bearing down on the squares around it.
+//
+// All of it is midgame only.  Once the queens and most of the pieces are off, the king wants to
+// be out in the middle of the board, and the king's piece-square table already says so.
+
+use constants::*;
+use board::Board;
+use bitboard::{square_index, square_to_pos};
+use types::PiecePosition;
+use util::to_color;
+use moves::attacks::{KING_ATTACKS, is_pos_attacked_by};
+use moves::generation::get_piece_attacks;
+
+use super::pawns::{file_mask, ranks_ahead};
+
+// Indexed by how many ranks in front of the king our nearest pawn on the file is, 0 for no pawn
+// in front at all.
+const SHIELD: [i32; 8] = [-20, 15, 8, 0, 0, 0, 0, 0];
+
+// Indexed by how many ranks in front of the king their nearest pawn on the file is.  Right up
+// against the king it's blocked by whatever is in the way, which is usually our own pawn.
+const STORM: [i32; 8] = [0, -5, -25, -15, -5, 0, 0, 0];
+
+// for each file next to the king with none of our pawns on it, and with none of theirs either
+const HALF_OPEN_FILE: i32 = -12;
+const OPEN_FILE: i32 = -25;
+
+// How much a piece adds to the attack for each square of the king zone it hits.
+const ATTACK_WEIGHTS: [i32; 6] = [0, 20, 20, 40, 80, 0];
+
+// The attack only comes to much with several pieces in on it, so the total is scaled by how many
+// there are, as a percentage.
+const ATTACKER_SCALE: [i32; 8] = [0, 0, 50, 75, 88, 94, 97, 99];
+
+// Ranks from `square` forward to the nearest pawn in `pawns`, or 0 if there isn't one.
+fn distance_to_nearest(color: Color, square: usize, pawns: u64) -> usize {
+    let in_front = pawns & file_mask(square % 8) & ranks_ahead(color, square / 8);
+    if in_front == 0 {
+        return 0;
+    }
+
+    let nearest = if color == WHITE { in_front.trailing_zeros() } else { 63 - in_front.leading_zeros() } as usize;
+    ((nearest / 8) as i32 - (square / 8) as i32).unsigned_abs() as usize
+}
+
+// Shield, storm and open files, for the files from one side of the king to the other.
+fn pawn_cover(board: &Board, color: Color, king: usize) -> i32 {
+    let ours = board.bb[to_color(W_PAWN, color == WHITE)];
+    let theirs = board.bb[to_color(W_PAWN, color != WHITE)];
+    let king_file = king % 8;
+    let mut score = 0;
+
+    for file in king_file.saturating_sub(1)..(king_file + 2).min(8) {
+        // the king's square, moved over onto this file
+        let square = king - king_file + file;
+
+        score += SHIELD[distance_to_nearest(color, square, ours)];
+        score += STORM[distance_to_nearest(color, square, theirs)];
+
+        if ours & file_mask(file) == 0 {
+            score += if theirs & file_mask(file) == 0 { OPEN_FILE } else { HALF_OPEN_FILE };
+        }
+    }
+
+    score
+}
+
+// Weighs up the pieces that attack the king and the squares around it.
+fn attack_units(board: &Board, color: Color, king: usize) -> i32 {
+    let zone = KING_ATTACKS[king] | 1 << king;
+    let enemy = color ^ 1;
+
+    // nothing gets near the king at all, so there's no need to look at the pieces one by one
+    let mut squares = zone;
+    let mut any_attacked = false;
+    while squares != 0 && !any_attacked {
+        any_attacked = is_pos_attacked_by(board, square_to_pos(squares.trailing_zeros() as usize), enemy);
+        squares &= squares - 1;
+    }
+
+    if !any_attacked {
+        return 0;
+    }
+
+    let (mut attackers, mut units) = (0, 0);
+    for class in KNIGHT..KING {
+        let piece = to_color(class, enemy == WHITE);
+        let mut pieces = board.bb[piece];
+
+        while pieces != 0 {
+            let pos = square_to_pos(pieces.trailing_zeros() as usize);
+            pieces &= pieces - 1;
+
+            let hits = get_piece_attacks(PiecePosition(piece, pos.0, pos.1), board)
+                .filter(|mv| zone & 1 << square_index(mv.dest_pos) != 0)
+                .count() as i32;
+
+            if hits > 0 {
+                attackers += 1;
+                units += ATTACK_WEIGHTS[class as usize] * hits;
+            }
+        }
+    }
+
+    -units * ATTACKER_SCALE[attackers.min(7)] / 100
+}
+
+// For `color`'s king.
+pub fn king_safety_for(board: &Board, color: Color) -> i32 {
+    let kings = board.bb[to_color(W_KING, color == WHITE)];
+    if kings == 0 {
+        return 0;
+    }
+
+    let king = kings.trailing_zeros() as usize;
+    pawn_cover(board, color, king) + attack_units(board, color, king)
+}
+
+// As (midgame, endgame), from white's point of view.
+pub fn king_safety(board: &Board) -> (i32, i32) {
+    (king_safety_for(board, WHITE) - king_safety_for(board, BLACK), 0)
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    fn safety(fen: &str, color: Color) -> i32 {
+        king_safety_for(&Board::from_fen(fen), color)
+    }
+
+    #[test]
+    fn pawn_shield() {
+        // castled behind unmoved pawns, then with one pushed, then with one gone
+        let intact = safety("4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1", WHITE);
+        let pushed = safety("4k3/8/8/8/8/6P1/5P1P/6K1 w - - 0 1", WHITE);
+        let missing = safety("4k3/8/8/8/8/8/5P1P/6K1 w - - 0 1", WHITE);
+
+        assert_eq!(intact, 3 * SHIELD[1]);
+        assert!(intact > pushed);
+        assert!(pushed > missing);
+    }
+
+    #[test]
+    fn open_files() {
+        // losing the g-pawn with black's still on the file, and with the file wide open
+        let half_open = safety("4k3/6p1/8/8/8/8/5P1P/6K1 w - - 0 1", WHITE);
+        let open = safety("4k3/8/8/8/8/8/5P1P/6K1 w - - 0 1", WHITE);
+
+        assert_eq!(open - half_open, OPEN_FILE - HALF_OPEN_FILE);
+        assert!(open < half_open);
+    }
+
+    #[test]
+    fn pawn_storm() {
+        let quiet = safety("4k3/6p1/8/8/8/8/5PPP/6K1 w - - 0 1", WHITE);
+        let storm = safety("4k3/8/8/8/8/6p1/5PPP/6K1 w - - 0 1", WHITE);
+
+        assert_eq!(storm, quiet + STORM[2]);
+    }
+
+    #[test]
+    fn attackers_near_the_king() {
+        let castled = "r5k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1";
+        let alone = safety(castled, WHITE);
+
+        // one piece on its own barely counts, two together do
+        let queen = safety("r5k1/5ppp/8/8/8/7q/5PPP/6K1 w - - 0 1", WHITE);
+        let queen_and_knight = safety("r5k1/5ppp/8/8/5n2/7q/5PPP/6K1 w - - 0 1", WHITE);
+
+        assert_eq!(queen, alone);
+        assert!(queen_and_knight < alone - 50, "{} vs {}", queen_and_knight, alone);
+    }
+
+    #[test]
+    fn safety_is_symmetric() {
+        let board = Board::from_fen("r5k1/5ppp/8/8/5n2/7q/5PPP/6K1 w - - 0 1");
+        let flipped = Board::from_fen("6k1/5ppp/7Q/5N2/8/8/5PPP/R5K1 w - - 0 1");
+
+        assert_eq!(king_safety_for(&board, WHITE), king_safety_for(&flipped, BLACK));
+        assert_eq!(king_safety(&board).0, -king_safety(&flipped).0);
+    }
+}
@@ -0,0 +1,309 @@
+pub mod pst;
+pub mod pawns;
+pub mod king;
+pub mod activity;
+
+// Static evaluation in centipawns, from white's point of view.
+//
+// Every term is kept as a pair of midgame and endgame values, which are blended by how much
+// material is left: with everything on the board the score is all midgame, and it slides over
+// to the endgame values as pieces come off.
+
+use constants::*;
+use board::Board;
+use board::utils::PieceIter;
+use moves::types::Move;
+use search::types::Score;
+
+use self::pst::{MG_TABLES, EG_TABLES, table_index};
+use self::pawns::{PawnEntry, PawnTable, pawn_structure, passed_pawn_paths};
+use self::king::king_safety;
+use self::activity::piece_activity;
+use std::cell::RefCell;
+
+// indexed by piece class, kings being on the board in every position
+pub const MG_VALUES: [i16; 6] = [82, 337, 365, 477, 1025, 0];
+pub const EG_VALUES: [i16; 6] = [94, 281, 297, 512, 936, 0];
+
+// Rough piece values in centipawns, for weighing up exchanges and ordering captures.  MG_VALUES and
+// EG_VALUES above are what the evaluation itself uses.
+pub fn get_piece_value(piece: PieceType) -> Score {
+    match piece {
+        NO_PIECE => 0,
+        W_PAWN => 100,
+        W_KNIGHT => 300,
+        W_BISHOP => 300,
+        W_ROOK => 500,
+        W_QUEEN => 1000,
+        W_KING => 10000,
+        
+        B_PAWN => -100,
+        B_KNIGHT => -300,
+        B_BISHOP => -300,
+        B_ROOK => -500,
+        B_QUEEN => -1000,
+        B_KING => -10000,
+        _ => 0,
+    }
+}
+
+// How much each piece class counts towards the game phase.  The starting position adds up to
+// MAX_PHASE, and promotions can't take it any higher.
+pub const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+pub const MAX_PHASE: i32 = 24;
+
+// From MAX_PHASE with all the pieces on down to 0 with only kings and pawns left.
+pub fn game_phase(board: &Board) -> i32 {
+    let mut phase = 0;
+
+    for class in PAWN..KING + 1 {
+        let count = board.bb[class] | board.bb[class + PIECE_TYPE_COLOR_OFFSET];
+        phase += PHASE_WEIGHTS[class as usize] * count.count_ones() as i32;
+    }
+
+    phase.min(MAX_PHASE)
+}
+
+// Blends a (midgame, endgame) pair for the given phase.
+pub fn taper(mg: i32, eg: i32, phase: i32) -> i32 {
+    (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+// Material and piece-square terms for both sides, as (midgame, endgame) from white's point of
+// view.
+fn piece_squares(board: &Board) -> (i32, i32) {
+    let (mut mg, mut eg) = (0, 0);
+
+    for &color in [WHITE, BLACK].iter() {
+        let sign = if color == WHITE { 1 } else { -1 };
+        let offset = if color == WHITE { 0 } else { PIECE_TYPE_COLOR_OFFSET };
+
+        for class in PAWN..KING + 1 {
+            let mut pieces = board.bb[class + offset];
+
+            while pieces != 0 {
+                let index = table_index(pieces.trailing_zeros() as usize, color);
+                pieces &= pieces - 1;
+
+                mg += sign * (MG_VALUES[class as usize] + MG_TABLES[class as usize][index]) as i32;
+                eg += sign * (EG_VALUES[class as usize] + EG_TABLES[class as usize][index]) as i32;
+            }
+        }
+    }
+
+    (mg, eg)
+}
+
+// Material and piece-square tables only.
+pub fn piece_square_score(board: &Board) -> Score {
+    let (mg, eg) = piece_squares(board);
+    taper(mg, eg, game_phase(board)) as Score
+}
+
+// everything, given the part that only depends on the pawns
+fn evaluate_with_pawns(board: &Board, pawns: &PawnEntry) -> Score {
+    let (mut mg, mut eg) = piece_squares(board);
+    let paths = passed_pawn_paths(board, pawns.passed);
+    let king = king_safety(board);
+    let activity = piece_activity(board);
+
+    mg += pawns.mg + paths.0 + king.0 + activity.0;
+    eg += pawns.eg + paths.1 + king.1 + activity.1;
+
+    scale_for_draws(board, taper(mg, eg, game_phase(board)) as Score)
+}
+
+// Whether `color` has enough left to mate without help: any pawn, rook or queen, or two minors
+// as long as one is a bishop, since two knights can't force it.
+fn can_force_mate(board: &Board, color: Color) -> bool {
+    let offset = if color == WHITE { 0 } else { PIECE_TYPE_COLOR_OFFSET };
+    let bishops = board.bb[BISHOP + offset];
+    let minors = board.bb[KNIGHT + offset] | bishops;
+
+    [PAWN, ROOK, QUEEN].iter().any(|&class| board.bb[class + offset] != 0)
+        || (minors.count_ones() >= 2 && bishops != 0)
+}
+
+// Dead draws score 0, and so does being ahead with nothing to mate with, however much better
+// placed the pieces are.
+fn scale_for_draws(board: &Board, score: Score) -> Score {
+    let ahead = if score > 0 { WHITE } else { BLACK };
+
+    if board.is_insufficient_material() || (score != 0 && !can_force_mate(board, ahead)) {
+        0
+    } else {
+        score
+    }
+}
+
+pub fn evaluate(board: &Board) -> Score {
+    evaluate_with_pawns(board, &pawn_structure(board))
+}
+
+// What the search scores its leaves with.  Scores are for the side to move.
+//
+// The search also tells the evaluator about every move it makes and takes back, null moves
+// included, so an implementation can keep its own state up to date instead of starting from
+// scratch at every leaf.  `board` is the position after the move in `move_made`, and the one it
+// was made from in `move_unmade`.
+pub trait Evaluator {
+    fn evaluate(&self, board: &Board) -> Score;
+
+    fn move_made(&self, _board: &Board, _mv: &Move) {}
+    fn move_unmade(&self, _board: &Board, _mv: &Move) {}
+}
+
+// from the side to move's point of view, given a score from white's
+fn for_side_to_move(board: &Board, score: Score) -> Score {
+    if board.to_move == WHITE { score } else { -score }
+}
+
+// Counts the pieces and nothing else.
+pub struct MaterialEvaluator { }
+
+impl Evaluator for MaterialEvaluator {
+    fn evaluate(&self, board: &Board) -> Score {
+        let sum = PieceIter::new(board).map(get_piece_value).sum();
+        for_side_to_move(board, sum)
+    }
+}
+
+// Material and piece-square tables, tapered by the game phase.
+pub struct PstEvaluator { }
+
+impl Evaluator for PstEvaluator {
+    fn evaluate(&self, board: &Board) -> Score {
+        for_side_to_move(board, piece_square_score(board))
+    }
+}
+
+// The full evaluation, with the pawn structure cached between calls.
+pub struct StandardEvaluator {
+    pawns: RefCell<PawnTable>,
+}
+
+impl StandardEvaluator {
+    pub fn new() -> Self {
+        StandardEvaluator { pawns: RefCell::new(PawnTable::new()) }
+    }
+}
+
+impl Default for StandardEvaluator {
+    fn default() -> Self {
+        StandardEvaluator::new()
+    }
+}
+
+impl Evaluator for StandardEvaluator {
+    fn evaluate(&self, board: &Board) -> Score {
+        let pawns = self.pawns.borrow_mut().probe(board);
+        for_side_to_move(board, evaluate_with_pawns(board, &pawns))
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    // the same position with the colours swapped and the board flipped
+    #[allow(dead_code)]
+    fn mirrored(fen: &str) -> String {
+        let fields = fen.split_whitespace().collect::<Vec<&str>>();
+
+        let swap_case = |c: char| if c.is_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() };
+        let pieces = fields[0].split('/').rev()
+            .map(|rank| rank.chars().map(swap_case).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("/");
+
+        let to_move = if fields[1] == "w" { "b" } else { "w" };
+        format!("{} {} - - 0 1", pieces, to_move)
+    }
+
+    #[test]
+    fn phase_follows_material() {
+        assert_eq!(game_phase(&Board::from_fen(START_FEN)), MAX_PHASE);
+        assert_eq!(game_phase(&Board::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1")), 0);
+        assert_eq!(game_phase(&Board::from_fen("3qk3/8/8/8/8/8/8/3QK1N1 w - - 0 1")), 9);
+
+        // extra queens don't push it past the start
+        assert_eq!(game_phase(&Board::from_fen("QQQQkQQQ/8/8/8/8/8/8/QQQQKQQQ w - - 0 1")), MAX_PHASE);
+    }
+
+    #[test]
+    fn evaluation_is_symmetric() {
+        let fens = [
+            START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "6bk/5p2/P4Pp1/5pP1/4pP1p/3pPp2/P2PpP2/4K3 w - - 0 1",
+            "8/2k5/3p4/p2P1p2/P2P1P2/8/8/4K3 w - - 0 1",
+        ];
+
+        assert_eq!(evaluate(&Board::from_fen(START_FEN)), 0);
+        for fen in fens.iter() {
+            let board = Board::from_fen(fen);
+            assert_eq!(evaluate(&board), -evaluate(&Board::from_fen(&mirrored(fen))), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn pieces_prefer_good_squares() {
+        // a knight in the centre beats one in the corner (with a pawn alongside, since a lone knight
+        // is a draw wherever it stands)
+        let centre = evaluate(&Board::from_fen("4k3/8/8/8/3N4/8/P7/4K3 w - - 0 1"));
+        let corner = evaluate(&Board::from_fen("4k3/8/8/8/8/8/P7/N3K3 w - - 0 1"));
+        assert!(centre > corner);
+
+        // with the pieces off, a passed pawn is worth more the further up it is and the king
+        // belongs in the middle
+        let far = evaluate(&Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1"));
+        let near = evaluate(&Board::from_fen("4k3/8/8/8/8/8/1P6/4K3 w - - 0 1"));
+        assert!(far > near + 100);
+
+        let central_king = evaluate(&Board::from_fen("7k/7p/8/8/3K4/8/P7/8 w - - 0 1"));
+        let corner_king = evaluate(&Board::from_fen("7k/7p/8/8/8/8/P7/K7 w - - 0 1"));
+        assert!(central_king > corner_king);
+    }
+
+    #[test]
+    fn nothing_to_mate_with_scores_zero() {
+        assert_eq!(evaluate(&Board::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1")), 0);
+        assert_eq!(evaluate(&Board::from_fen("4k2b/8/8/8/8/4N3/8/4K3 b - - 0 1")), 0);
+        assert_eq!(evaluate(&Board::from_fen("4kb2/8/8/8/8/8/8/2B1K1B1 w - - 0 1")), 0);
+        assert_eq!(evaluate(&Board::from_fen("4k3/8/8/8/8/8/8/1N2K1N1 w - - 0 1")), 0);
+        assert!(evaluate(&Board::from_fen("4k3/8/8/8/8/8/8/1N2KB2 w - - 0 1")) > 0);
+
+        // a bishop against a pawn is more material, but only the pawn can ever win
+        assert_eq!(evaluate(&Board::from_fen("4k3/p7/8/8/8/4B3/8/4K3 w - - 0 1")), 0);
+        assert!(evaluate(&Board::from_fen("4k2b/8/8/8/8/4N3/P7/4K3 w - - 0 1")) > 0);
+    }
+
+    #[test]
+    fn values_are_in_centipawns() {
+        // a queen up in the opening
+        let score = piece_square_score(&Board::from_fen("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"));
+        assert!(score > 900 && score < 1150, "{}", score);
+
+        // a pawn up in a king and pawn ending
+        let score = piece_square_score(&Board::from_fen("4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1"));
+        let even = piece_square_score(&Board::from_fen("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1"));
+        assert!(score - even > 80 && score - even < 130, "{}", score - even);
+    }
+
+    #[test]
+    fn evaluators_score_for_the_side_to_move() {
+        let evaluators: [&dyn Evaluator; 3] = [&MaterialEvaluator {}, &PstEvaluator {}, &StandardEvaluator::new()];
+
+        for evaluator in evaluators.iter() {
+            let white = evaluator.evaluate(&Board::from_fen("4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1"));
+            let black = evaluator.evaluate(&Board::from_fen("4k3/8/8/8/8/8/3PP3/4K3 b - - 0 1"));
+            assert!(white > 0);
+            assert_eq!(white, -black);
+        }
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1");
+        assert_eq!(MaterialEvaluator {}.evaluate(&board), 200);
+        assert_eq!(PstEvaluator {}.evaluate(&board), piece_square_score(&board));
+        assert_eq!(StandardEvaluator::new().evaluate(&board), evaluate(&board));
+    }
+}
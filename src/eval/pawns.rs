@@ -0,0 +1,272 @@
+// Pawn structure: doubled, isolated, backward, connected and passed pawns.
+//
+// Everything here except how free a passed pawn's path is depends on the pawns alone, so it's
+// worked out once per pawn structure and kept in a `PawnTable` keyed on `Board.pawn_hash`.
+
+use constants::*;
+use board::Board;
+use bitboard::ALL;
+use moves::attacks::PAWN_ATTACKS;
+
+// (midgame, endgame) penalties, per pawn
+const DOUBLED: (i32, i32) = (-10, -25);
+const ISOLATED: (i32, i32) = (-8, -15);
+const BACKWARD: (i32, i32) = (-8, -12);
+
+// Indexed by how far up the board the pawn is from its own side, 0 being the back rank.  A pawn
+// is connected when another pawn of its colour stands beside it or defends it.
+const CONNECTED: [i32; 8] = [0, 4, 6, 10, 18, 30, 50, 0];
+const PASSED_MG: [i32; 8] = [0, 5, 10, 15, 25, 40, 60, 0];
+const PASSED_EG: [i32; 8] = [0, 10, 15, 25, 45, 70, 110, 0];
+
+// extra for a passed pawn with nothing at all standing in front of it, endgame only since that's
+// when a pawn can run
+const FREE_PATH: [i32; 8] = [0, 0, 5, 10, 20, 35, 60, 0];
+
+// enough for the pawn structures a single search comes across
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+
+pub fn file_mask(file: usize) -> u64 {
+    FILE_A << file
+}
+
+pub fn adjacent_files(file: usize) -> u64 {
+    let left = if file > 0 { file_mask(file - 1) } else { 0 };
+    let right = if file < 7 { file_mask(file + 1) } else { 0 };
+    left | right
+}
+
+// every square on the ranks in front of `rank`, as `color` sees it
+pub fn ranks_ahead(color: Color, rank: usize) -> u64 {
+    match color {
+        WHITE if rank < 7 => !0 << (8 * (rank + 1)),
+        BLACK if rank > 0 => !0 >> (8 * (8 - rank)),
+        _ => 0,
+    }
+}
+
+pub fn relative_rank(color: Color, square: usize) -> usize {
+    if color == WHITE { square / 8 } else { 7 - square / 8 }
+}
+
+// the square a pawn pushes to
+fn stop_square(color: Color, square: usize) -> usize {
+    if color == WHITE { square + 8 } else { square - 8 }
+}
+
+// The pawn-only part of the evaluation, from white's point of view.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PawnEntry {
+    pub key: u64,
+    pub mg: i32,
+    pub eg: i32,
+
+    // both sides' passed pawns
+    pub passed: u64,
+}
+
+impl PawnEntry {
+    const EMPTY: PawnEntry = PawnEntry { key: 0, mg: 0, eg: 0, passed: 0 };
+}
+
+pub fn pawn_structure(board: &Board) -> PawnEntry {
+    let mut entry = PawnEntry { key: board.pawn_hash, ..PawnEntry::EMPTY };
+
+    for &color in [WHITE, BLACK].iter() {
+        let sign = if color == WHITE { 1 } else { -1 };
+        let ours = board.bb[if color == WHITE { W_PAWN } else { B_PAWN }];
+        let theirs = board.bb[if color == WHITE { B_PAWN } else { W_PAWN }];
+        let (mut mg, mut eg) = (0, 0);
+
+        let mut pawns = ours;
+        while pawns != 0 {
+            let square = pawns.trailing_zeros() as usize;
+            pawns &= pawns - 1;
+
+            let file = square % 8;
+            let rank = relative_rank(color, square);
+            let ahead = ranks_ahead(color, square / 8);
+            let neighbours = ours & adjacent_files(file);
+
+            // every pawn with another of its own further up the file counts as doubled
+            if ours & file_mask(file) & ahead != 0 {
+                mg += DOUBLED.0;
+                eg += DOUBLED.1;
+            }
+
+            // Isolated: no pawns at all on the files either side.  Backward: they've all gone on,
+            // so none can come up in support, and pushing it walks into a capture.
+            if neighbours == 0 {
+                mg += ISOLATED.0;
+                eg += ISOLATED.1;
+            } else if neighbours & !ahead == 0 && PAWN_ATTACKS[color as usize][stop_square(color, square)] & theirs != 0 {
+                mg += BACKWARD.0;
+                eg += BACKWARD.1;
+            }
+
+            let beside = neighbours & (0xff << (8 * (square / 8)));
+            let defenders = PAWN_ATTACKS[(color ^ 1) as usize][square] & ours;
+            if beside | defenders != 0 {
+                mg += CONNECTED[rank];
+                eg += CONNECTED[rank];
+            }
+
+            let span = (file_mask(file) | adjacent_files(file)) & ahead;
+            if theirs & span == 0 && ours & file_mask(file) & ahead == 0 {
+                entry.passed |= 1 << square;
+                mg += PASSED_MG[rank];
+                eg += PASSED_EG[rank];
+            }
+        }
+
+        entry.mg += sign * mg;
+        entry.eg += sign * eg;
+    }
+
+    entry
+}
+
+// The part of the passed pawn terms that depends on the other pieces, as (midgame, endgame) from
+// white's point of view.
+pub fn passed_pawn_paths(board: &Board, passed: u64) -> (i32, i32) {
+    let occupied = board.bb[ALL];
+    let mut eg = 0;
+
+    let mut pawns = passed;
+    while pawns != 0 {
+        let square = pawns.trailing_zeros() as usize;
+        pawns &= pawns - 1;
+
+        let color = if board.bb[W_PAWN] & (1 << square) != 0 { WHITE } else { BLACK };
+        let path = file_mask(square % 8) & ranks_ahead(color, square / 8);
+
+        if occupied & path == 0 {
+            let bonus = FREE_PATH[relative_rank(color, square)];
+            eg += if color == WHITE { bonus } else { -bonus };
+        }
+    }
+
+    (0, eg)
+}
+
+// Pawn structures seen before, one slot per low bits of the key.
+//
+// The slots are only allocated on the first probe, as every `SearchParams` comes with an evaluator
+// and most of those never evaluate anything.
+pub struct PawnTable {
+    entries: Vec<PawnEntry>,
+}
+
+impl PawnTable {
+    pub fn new() -> Self {
+        PawnTable { entries: vec![] }
+    }
+
+    // the entry for the board's pawns, working it out if it isn't in the table already
+    pub fn probe(&mut self, board: &Board) -> PawnEntry {
+        if self.entries.is_empty() {
+            self.entries = vec![PawnEntry::EMPTY; PAWN_TABLE_SIZE];
+        }
+
+        let slot = board.pawn_hash as usize % self.entries.len();
+
+        // an empty slot has key 0, which is also the key with no pawns on the board at all
+        if self.entries[slot].key != board.pawn_hash || board.pawn_hash == 0 {
+            self.entries[slot] = pawn_structure(board);
+        }
+
+        self.entries[slot]
+    }
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        PawnTable::new()
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    fn structure(fen: &str) -> PawnEntry {
+        pawn_structure(&Board::from_fen(fen))
+    }
+
+    #[test]
+    fn doubled_and_isolated() {
+        // the same three pawns against a single one, either spread out or piled up
+        let healthy = structure("4k3/3p4/8/8/8/8/2PPP3/4K3 w - - 0 1");
+        let doubled = structure("4k3/3p4/8/8/8/3P4/2P1P3/4K3 w - - 0 1");
+        let wrecked = structure("4k3/3p4/8/8/4P3/4P3/4P3/4K3 w - - 0 1");
+
+        assert!(doubled.eg < healthy.eg);
+        assert!(wrecked.eg < doubled.eg);
+        assert_eq!(structure("4k3/8/8/8/8/8/P7/4K3 w - - 0 1").mg, ISOLATED.0 + PASSED_MG[1]);
+    }
+
+    #[test]
+    fn backward_pawns() {
+        // d3 can't advance without being taken by the pawn on c5 or e5, and the c- and e-pawns have
+        // gone on without it
+        let backward = structure("4k3/8/8/2p1p3/2P1P3/3P4/8/4K3 w - - 0 1");
+        let supported = structure("4k3/8/8/2p1p3/8/2PPP3/8/4K3 w - - 0 1");
+
+        assert!(backward.mg < supported.mg);
+    }
+
+    #[test]
+    fn connected_pawns() {
+        let apart = structure("4k3/8/8/8/8/8/P1P5/4K3 w - - 0 1");
+        let beside = structure("4k3/8/8/8/8/8/PP6/4K3 w - - 0 1");
+        let chain = structure("4k3/8/8/8/8/1P6/P7/4K3 w - - 0 1");
+
+        assert!(beside.mg > apart.mg);
+        assert!(chain.mg > apart.mg);
+    }
+
+    #[test]
+    fn passed_pawns() {
+        // the b-pawn has nothing in front of it, the e-pawn has the d7 pawn to get past
+        let entry = structure("4k3/3p4/8/1P6/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(entry.passed, 1 << 33);
+
+        // worth more the further up it is, and more again when nothing stands in its way
+        let far = structure("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+        let near = structure("4k3/8/8/8/8/1P6/8/4K3 w - - 0 1");
+        assert!(far.eg > near.eg);
+
+        let free = Board::from_fen("4k3/8/8/1P6/8/8/8/4K3 w - - 0 1");
+        let blocked = Board::from_fen("1n2k3/8/8/1P6/8/8/8/4K3 w - - 0 1");
+        assert_eq!(passed_pawn_paths(&free, 1 << 33), (0, FREE_PATH[4]));
+        assert_eq!(passed_pawn_paths(&blocked, 1 << 33), (0, 0));
+        assert_eq!(passed_pawn_paths(&Board::from_fen("4k3/8/8/8/1p6/8/8/4K3 w - - 0 1"), 1 << 25), (0, -FREE_PATH[4]));
+    }
+
+    #[test]
+    fn colours_mirror_each_other() {
+        let white = structure("4k3/8/8/8/2P5/2P5/P7/4K3 w - - 0 1");
+        let black = structure("4k3/p7/2p5/2p5/8/8/8/4K3 w - - 0 1");
+        assert_eq!((white.mg, white.eg), (-black.mg, -black.eg));
+    }
+
+    #[test]
+    fn table_remembers_structures() {
+        let mut table = PawnTable::new();
+        let board = Board::from_fen("4k3/3p4/8/1P6/4P3/8/8/4K3 w - - 0 1");
+        assert!(table.entries.is_empty());
+
+        let entry = table.probe(&board);
+        assert_eq!(table.entries.len(), PAWN_TABLE_SIZE);
+        assert_eq!(entry, pawn_structure(&board));
+        assert_eq!(entry.key, board.pawn_hash);
+
+        // the pieces don't matter, only the pawns
+        let with_pieces = Board::from_fen("r3k3/3p4/8/1P6/4P3/8/8/4K2R w - - 0 1");
+        assert_eq!(with_pieces.pawn_hash, board.pawn_hash);
+        assert_eq!(table.probe(&with_pieces), entry);
+    }
+}
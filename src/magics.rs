@@ -0,0 +1,216 @@
+// Magic bitboard lookups for sliding piece attacks.
+//
+// For each square the relevant blockers (the ray squares, minus the edge squares) are masked out
+// of the occupancy, multiplied by a magic number and shifted down to an index into a table of
+// precomputed attack sets.  The magics are searched for once, on first use or by `init`, with a
+// fixed seed so that the tables are the same on every run.
+
+use std::sync::OnceLock;
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+#[derive(Copy, Clone, Default)]
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupancy: u64) -> usize {
+        self.offset + ((occupancy & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct MagicTables {
+    rook: [Magic; 64],
+    bishop: [Magic; 64],
+    attacks: Vec<u64>,
+}
+
+static TABLES: OnceLock<MagicTables> = OnceLock::new();
+
+fn tables() -> &'static MagicTables {
+    TABLES.get_or_init(MagicTables::new)
+}
+
+// Builds the tables now rather than on the first slider lookup, which would otherwise come out of
+// the first search's time.
+pub fn init() {
+    tables();
+}
+
+// Squares attacked by a rook on `square` (a1 = 0, h8 = 63) given the occupied squares.  The
+// attack set includes the first blocker in each direction, whatever its color.
+pub fn rook_attacks(square: usize, occupancy: u64) -> u64 {
+    let t = tables();
+    t.attacks[t.rook[square].index(occupancy)]
+}
+
+pub fn bishop_attacks(square: usize, occupancy: u64) -> u64 {
+    let t = tables();
+    t.attacks[t.bishop[square].index(occupancy)]
+}
+
+pub fn queen_attacks(square: usize, occupancy: u64) -> u64 {
+    rook_attacks(square, occupancy) | bishop_attacks(square, occupancy)
+}
+
+impl MagicTables {
+    fn new() -> Self {
+        let mut tables = MagicTables {
+            rook: [Magic::default(); 64],
+            bishop: [Magic::default(); 64],
+            attacks: Vec::with_capacity(0x19000 + 0x1480),
+        };
+
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+
+        for square in 0..64 {
+            tables.rook[square] = find_magic(square, &ROOK_DIRECTIONS, &mut tables.attacks, &mut rng);
+            tables.bishop[square] = find_magic(square, &BISHOP_DIRECTIONS, &mut tables.attacks, &mut rng);
+        }
+
+        tables
+    }
+}
+
+// Walks each direction from `square` until the edge or the first occupied square.
+fn slow_attacks(square: usize, directions: &[(i8, i8)], occupancy: u64) -> u64 {
+    let mut attacks = 0;
+
+    for &(df, dr) in directions {
+        let (mut f, mut r) = ((square % 8) as i8 + df, (square / 8) as i8 + dr);
+
+        while (0..8).contains(&f) && (0..8).contains(&r) {
+            let bit = 1u64 << (r * 8 + f);
+            attacks |= bit;
+
+            if occupancy & bit != 0 {
+                break;
+            }
+
+            f += df;
+            r += dr;
+        }
+    }
+
+    attacks
+}
+
+// The squares whose occupancy matters: every ray square except the last one in each direction,
+// since a blocker there doesn't hide anything.
+fn relevant_mask(square: usize, directions: &[(i8, i8)]) -> u64 {
+    let mut mask = 0;
+
+    for &(df, dr) in directions {
+        let (mut f, mut r) = ((square % 8) as i8 + df, (square / 8) as i8 + dr);
+
+        while f + df >= 0 && f + df < 8 && r + dr >= 0 && r + dr < 8 {
+            mask |= 1u64 << (r * 8 + f);
+            f += df;
+            r += dr;
+        }
+    }
+
+    mask
+}
+
+fn find_magic(square: usize, directions: &[(i8, i8)], attacks: &mut Vec<u64>, rng: &mut XorShift) -> Magic {
+    let mask = relevant_mask(square, directions);
+    let bits = mask.count_ones();
+    let size = 1usize << bits;
+
+    // enumerate every subset of the mask (carry-rippler) along with its attack set
+    let mut occupancies = Vec::with_capacity(size);
+    let mut references = Vec::with_capacity(size);
+    let mut subset: u64 = 0;
+    loop {
+        occupancies.push(subset);
+        references.push(slow_attacks(square, directions, subset));
+
+        subset = subset.wrapping_sub(mask) & mask;
+        if subset == 0 {
+            break;
+        }
+    }
+
+    let offset = attacks.len();
+    attacks.resize(offset + size, 0);
+
+    // which attempt last wrote each slot, so the table doesn't need clearing between attempts
+    let mut epoch = vec![0u32; size];
+    let mut attempt = 0;
+
+    loop {
+        let magic = rng.sparse();
+        if (mask.wrapping_mul(magic) >> 56).count_ones() < 6 {
+            continue;
+        }
+
+        attempt += 1;
+
+        let candidate = Magic { mask, magic, shift: 64 - bits, offset };
+        let mut ok = true;
+
+        for i in 0..size {
+            let index = candidate.index(occupancies[i]);
+
+            if epoch[index - offset] != attempt {
+                epoch[index - offset] = attempt;
+                attacks[index] = references[i];
+            } else if attacks[index] != references[i] {
+                ok = false;
+                break;
+            }
+        }
+
+        if ok {
+            return candidate;
+        }
+    }
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // magics with few bits set are found much faster
+    fn sparse(&mut self) -> u64 {
+        self.next() & self.next() & self.next()
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn lookups_match_ray_walk() {
+        let mut rng = XorShift(7);
+
+        for square in 0..64 {
+            for _ in 0..50 {
+                let occupancy = rng.sparse() | rng.sparse();
+
+                assert_eq!(rook_attacks(square, occupancy), slow_attacks(square, &ROOK_DIRECTIONS, occupancy));
+                assert_eq!(bishop_attacks(square, occupancy), slow_attacks(square, &BISHOP_DIRECTIONS, occupancy));
+            }
+        }
+    }
+
+    #[test]
+    fn rook_on_empty_board() {
+        // a1 sees the whole first rank and a-file
+        assert_eq!(rook_attacks(0, 0), 0x0101_0101_0101_01fe);
+        assert_eq!(queen_attacks(0, 0), 0x8040_2010_0804_0200 | 0x0101_0101_0101_01fe);
+    }
+}
@@ -1,23 +1,19 @@
-#![allow(dead_code)]
-#![allow(unused_assignments)]
-#![allow(unused_variables)]
-#![allow(unused_imports)]
-#![allow(unused_mut)]
-#![allow(unused_parens)]
-#[macro_use]
-
 pub mod util;
 pub mod types;
 pub mod constants;
 pub mod board;
 pub mod bitboard;
+pub mod magics;
 pub mod zobrist;
 pub mod moves;
+pub mod eval;
 pub mod search;
+pub mod engine;
+pub mod pgn;
 
 extern crate regex;
 extern crate rand;
 
 fn main() {
-    
+    engine::uci::main_loop();
 }
@@ -0,0 +1,232 @@
+// Table driven attack queries.
+//
+// The 0x88 layout of the mailbox means that the difference between two square indexes (plus 119)
+// identifies the direction and distance between them, independent of where they are on the
+// board.  `ATTACK_KINDS` records which kinds of piece could attack along each difference and
+// `ATTACK_STEP` the single step delta to walk for sliders, so checking whether a piece attacks a
+// square is a lookup plus, for sliders, a walk over the squares in between.  Knights, kings and
+// pawns are answered straight from precomputed bitboards.
+
+use board::Board;
+use bitboard::{square_index, square_to_pos};
+use constants::*;
+use types::{Position, PiecePosition};
+use util::{to_white, to_color, opposite_color};
+
+const DIAGONAL: u8 = 0x01;
+const ORTHOGONAL: u8 = 0x02;
+const KNIGHT_JUMP: u8 = 0x04;
+const KING_STEP: u8 = 0x08;
+const WHITE_PAWN_CAPTURE: u8 = 0x10;
+const BLACK_PAWN_CAPTURE: u8 = 0x20;
+
+const DIFF_OFFSET: i16 = 119;
+
+const KNIGHT_DELTAS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_DELTAS: [(i8, i8); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+
+static ATTACK_KINDS: [u8; 239] = build_attack_kinds();
+static ATTACK_STEP: [i8; 239] = build_attack_steps();
+
+pub static KNIGHT_ATTACKS: [u64; 64] = build_leaper_attacks(&KNIGHT_DELTAS);
+pub static KING_ATTACKS: [u64; 64] = build_leaper_attacks(&KING_DELTAS);
+
+// indexed by the pawn's color, then its square
+pub static PAWN_ATTACKS: [[u64; 64]; 2] = [
+    build_leaper_attacks(&[(-1, 1), (1, 1)]),
+    build_leaper_attacks(&[(-1, -1), (1, -1)]),
+];
+
+const fn build_attack_kinds() -> [u8; 239] {
+    let mut kinds = [0u8; 239];
+
+    let mut i = 0;
+    while i < 8 {
+        let (df, dr) = KING_DELTAS[i];
+        let mut n = 1;
+        while n < 8 {
+            let diff = (dr as i16 * n * 16 + df as i16 * n + DIFF_OFFSET) as usize;
+            kinds[diff] |= if df == 0 || dr == 0 { ORTHOGONAL } else { DIAGONAL };
+            n += 1;
+        }
+
+        kinds[(dr as i16 * 16 + df as i16 + DIFF_OFFSET) as usize] |= KING_STEP;
+
+        let (nf, nr) = KNIGHT_DELTAS[i];
+        kinds[(nr as i16 * 16 + nf as i16 + DIFF_OFFSET) as usize] |= KNIGHT_JUMP;
+        i += 1;
+    }
+
+    kinds[(DIFF_OFFSET + 15) as usize] |= WHITE_PAWN_CAPTURE;
+    kinds[(DIFF_OFFSET + 17) as usize] |= WHITE_PAWN_CAPTURE;
+    kinds[(DIFF_OFFSET - 15) as usize] |= BLACK_PAWN_CAPTURE;
+    kinds[(DIFF_OFFSET - 17) as usize] |= BLACK_PAWN_CAPTURE;
+
+    kinds
+}
+
+const fn build_attack_steps() -> [i8; 239] {
+    let mut steps = [0i8; 239];
+
+    let mut i = 0;
+    while i < 8 {
+        let (df, dr) = KING_DELTAS[i];
+        let step = dr * 16 + df;
+        let mut n = 1;
+        while n < 8 {
+            steps[(step as i16 * n + DIFF_OFFSET) as usize] = step;
+            n += 1;
+        }
+        i += 1;
+    }
+
+    steps
+}
+
+const fn build_leaper_attacks(deltas: &[(i8, i8)]) -> [u64; 64] {
+    let mut attacks = [0u64; 64];
+
+    let mut square = 0;
+    while square < 64 {
+        let (f, r) = ((square % 8) as i8, (square / 8) as i8);
+
+        let mut i = 0;
+        while i < deltas.len() {
+            let (df, dr) = deltas[i];
+            let (tf, tr) = (f + df, r + dr);
+            if tf >= 0 && tf < 8 && tr >= 0 && tr < 8 {
+                attacks[square] |= 1u64 << (tr * 8 + tf);
+            }
+            i += 1;
+        }
+
+        square += 1;
+    }
+
+    attacks
+}
+
+fn index_0x88(pos: Position) -> i16 {
+    ((pos.1 as i16) << 4) + pos.0 as i16
+}
+
+// Does the piece attack the target square?  Friendly pieces on the target count as attacked,
+// the same as `get_piece_attacks`.
+pub fn does_piece_attack(board: &Board, piece: PiecePosition, target: Position) -> bool {
+    let origin = index_0x88(piece.to_position());
+    let diff = index_0x88(target) - origin;
+    if diff == 0 {
+        return false;
+    }
+
+    let kinds = ATTACK_KINDS[(diff + DIFF_OFFSET) as usize];
+    let needed = match to_white(piece.0) {
+        W_PAWN => if piece.0 == W_PAWN { WHITE_PAWN_CAPTURE } else { BLACK_PAWN_CAPTURE },
+        W_KNIGHT => KNIGHT_JUMP,
+        W_BISHOP => DIAGONAL,
+        W_ROOK => ORTHOGONAL,
+        W_QUEEN => DIAGONAL | ORTHOGONAL,
+        W_KING => KING_STEP,
+        _ => 0,
+    };
+
+    if kinds & needed == 0 {
+        return false;
+    }
+
+    match to_white(piece.0) {
+        W_BISHOP | W_ROOK | W_QUEEN => is_path_clear(board, origin, diff),
+        _ => true,
+    }
+}
+
+// Are the squares strictly between origin and origin + diff empty?
+fn is_path_clear(board: &Board, origin: i16, diff: i16) -> bool {
+    let step = ATTACK_STEP[(diff + DIFF_OFFSET) as usize] as i16;
+    let target = origin + diff;
+
+    let mut sq = origin + step;
+    while sq != target {
+        if board.mb.0[sq as usize] != NO_PIECE {
+            return false;
+        }
+        sq += step;
+    }
+
+    true
+}
+
+pub fn is_pos_attacked_by(board: &Board, pos: Position, color: Color) -> bool {
+    let square = square_index(pos);
+    let is_white = color == WHITE;
+    let bb = &board.bb;
+
+    // a pawn of `color` attacks the square if a pawn of the other color on the square would
+    // attack the pawn back
+    let leapers = (PAWN_ATTACKS[opposite_color(color) as usize][square] & bb[to_color(W_PAWN, is_white)])
+        | (KNIGHT_ATTACKS[square] & bb[to_color(W_KNIGHT, is_white)])
+        | (KING_ATTACKS[square] & bb[to_color(W_KING, is_white)]);
+
+    if leapers != 0 {
+        return true;
+    }
+
+    let target = index_0x88(pos);
+    let queens = bb[to_color(W_QUEEN, is_white)];
+    let sliders = [
+        (bb[to_color(W_BISHOP, is_white)] | queens, DIAGONAL),
+        (bb[to_color(W_ROOK, is_white)] | queens, ORTHOGONAL),
+    ];
+
+    for &(mut pieces, kind) in sliders.iter() {
+        while pieces != 0 {
+            let origin = index_0x88(square_to_pos(pieces.trailing_zeros() as usize));
+            pieces &= pieces - 1;
+
+            let diff = target - origin;
+            if ATTACK_KINDS[(diff + DIFF_OFFSET) as usize] & kind != 0 && is_path_clear(board, origin, diff) {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use moves::generation::get_piece_attacks;
+
+    #[test]
+    fn leaper_tables() {
+        // knight on a1 hits b3 and c2, king on e1 has five squares
+        assert_eq!(KNIGHT_ATTACKS[0], (1 << 17) | (1 << 10));
+        assert_eq!(KING_ATTACKS[4].count_ones(), 5);
+
+        // white pawn on e2 hits d3 and f3, black pawn on a7 only b6
+        assert_eq!(PAWN_ATTACKS[WHITE as usize][12], (1 << 19) | (1 << 21));
+        assert_eq!(PAWN_ATTACKS[BLACK as usize][48], 1 << 41);
+    }
+
+    #[test]
+    fn does_piece_attack_matches_generation() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+
+        for piece in board.get_pieces() {
+            let generated = get_piece_attacks(piece, &board).map(|m| m.dest_pos).collect::<Vec<Position>>();
+
+            for f in 0..FILE_COUNT {
+                for r in 0..RANK_COUNT {
+                    let target = Position(f, r);
+                    assert_eq!(
+                        does_piece_attack(&board, piece, target),
+                        generated.contains(&target),
+                        "{:?} -> {:?}", piece, target
+                    );
+                }
+            }
+        }
+    }
+}
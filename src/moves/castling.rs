@@ -1,3 +1,5 @@
+#![allow(dead_code, unused_imports)]
+#![allow(clippy::len_zero, clippy::redundant_static_lifetimes)]
 
 use self::types::*;
 use types::*;
@@ -31,7 +33,7 @@ pub fn generate_castling_moves(piece: PiecePosition, board: &Board) -> MovesIter
         if board.castling & W_OOO > 0
                 && board.mb.get(QUEEN_SIDE_CASTLE_FILE + 1, WHITE_BACK_RANK) == NO_PIECE
                 && board.mb.get(QUEEN_SIDE_CASTLE_FILE, WHITE_BACK_RANK) == NO_PIECE
-                && board.mb.get(QUEEN_SIDE_CASTLE_FILE - 1, BLACK_BACK_RANK) == NO_PIECE
+                && board.mb.get(QUEEN_SIDE_CASTLE_FILE - 1, WHITE_BACK_RANK) == NO_PIECE
                 && !is_pos_attacked_by(board, Position(QUEEN_SIDE_CASTLE_FILE, WHITE_BACK_RANK), BLACK) 
                 && !is_pos_attacked_by(board, Position(QUEEN_SIDE_CASTLE_FILE + 1, WHITE_BACK_RANK), BLACK)
                 && !is_pos_attacked_by(board, Position(QUEEN_SIDE_CASTLE_FILE + 2, WHITE_BACK_RANK), BLACK) {
@@ -1,16 +1,21 @@
+#![allow(dead_code, unused_imports, unused_mut)]
+#![allow(clippy::let_and_return, clippy::needless_bool, clippy::redundant_field_names, clippy::unnecessary_cast, clippy::vec_init_then_push)]
+
 use std::iter;
 
 use super::castling;
+use super::legal::LegalityInfo;
 use util::to_white;
 use board::{Board};
 use board::mailbox::{Mailbox};
 
 use types::{Position, PiecePosition};
-use super::{would_move_cause_check, is_pos_attacked_by};
+use super::is_pos_attacked_by;
 use util::{
     is_occupied_and_enemy, 
     opposite_color, 
     is_same_color,
+    is_white,
     color_of,
 
 };
@@ -25,13 +30,50 @@ use super::types::{
 };
 
 use constants::*;
+use bitboard::{ALL, ALL_WHITE, ALL_BLACK, square_index, square_to_pos};
+use super::attacks::{KNIGHT_ATTACKS, KING_ATTACKS, PAWN_ATTACKS};
+use magics;
 use constants::{ PieceType };
 
-// TODO: write a stateful move generator trait that generates moves without doing 
-// redundant work.
+// Which of the legal moves to generate.  Promotions go with the captures since, like them, they
+// change the material balance; everything else, castling included, is quiet.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MoveKind {
+    All,
+    Captures,
+    Quiets,
+}
+
+impl MoveKind {
+    fn includes(&self, mv: &Move) -> bool {
+        match *self {
+            MoveKind::All => true,
+            MoveKind::Captures => mv.is_capture() || mv.is_promotion(),
+            MoveKind::Quiets => !mv.is_capture() && !mv.is_promotion(),
+        }
+    }
+}
+
+// When moves are wanted one at a time, best first, use `picker::MovePicker` instead.
 pub fn generate_all_moves_for_color(board: &Board, color: Color) -> MoveList {
+    generate_moves_of_kind(board, color, MoveKind::All)
+}
+
+// captures, en passant and promotions
+pub fn generate_captures(board: &Board, color: Color) -> MoveList {
+    generate_moves_of_kind(board, color, MoveKind::Captures)
+}
+
+// every legal move that `generate_captures` leaves out
+pub fn generate_quiets(board: &Board, color: Color) -> MoveList {
+    generate_moves_of_kind(board, color, MoveKind::Quiets)
+}
+
+pub fn generate_moves_of_kind(board: &Board, color: Color, kind: MoveKind) -> MoveList {
+    let legality = LegalityInfo::new(board, color);
+
     let all_moves = board.get_pieces_of_color(color).iter().flat_map(|piece_pos: &PiecePosition| {
-        let moves = generate_moves_for_piece(*piece_pos, board);
+        let moves = generate_legal_moves_for_piece(*piece_pos, board, &legality, kind);
         moves 
     }).collect::<Vec<Move>>();
 
@@ -40,32 +82,53 @@ pub fn generate_all_moves_for_color(board: &Board, color: Color) -> MoveList {
 }
 
 pub fn generate_moves_for_piece(piece: PiecePosition, board: &Board) -> MoveList {
+    generate_legal_moves_for_piece(piece, board, &LegalityInfo::new(board, color_of(piece.0)), MoveKind::All)
+}
+
+fn generate_legal_moves_for_piece(piece: PiecePosition, board: &Board, legality: &LegalityInfo, kind: MoveKind) -> MoveList {
+    // with two pieces giving check only the king can move
+    if legality.checkers.count_ones() > 1 && to_white(piece.0) != KING {
+        return vec![];
+    }
+
+    let square = square_index(piece.to_position());
+    let occupancy = board.bb[ALL];
+    let enemy = board.bb[if is_white(piece.0) { ALL_BLACK } else { ALL_WHITE }];
+
+    // leapers and sliders only look at the squares that can hold the kind of move asked for, and
+    // apart from the king, only at squares that deal with a check
+    let targets = match kind {
+        MoveKind::All => !(occupancy & !enemy),
+        MoveKind::Captures => enemy,
+        MoveKind::Quiets => !occupancy,
+    };
+    let piece_targets = targets & legality.evasion_mask;
+
     let moves = match to_white(piece.0) {
-        PAWN => generate_pawn_moves(piece, board, true, false),
-        KNIGHT => generate_knight_moves(piece, board, false),
-        BISHOP => generate_bishop_moves(piece, board, false),
-        ROOK => generate_rook_moves(piece, board, false),
-        QUEEN => generate_queen_moves(piece, board, false),
-        KING => generate_king_moves(piece, board, false),
-        _ => MovesIter::from_vec(vec![])
+        PAWN => generate_pawn_moves(piece, board, true, false).filter(|mv| kind.includes(mv)).collect(),
+        KNIGHT => moves_from_attack_set(piece, KNIGHT_ATTACKS[square] & piece_targets, board, false),
+        BISHOP => moves_from_attack_set(piece, magics::bishop_attacks(square, occupancy) & piece_targets, board, false),
+        ROOK => moves_from_attack_set(piece, magics::rook_attacks(square, occupancy) & piece_targets, board, false),
+        QUEEN => moves_from_attack_set(piece, magics::queen_attacks(square, occupancy) & piece_targets, board, false),
+        KING => {
+            let mut moves = moves_from_attack_set(piece, KING_ATTACKS[square] & targets, board, false);
+            if kind != MoveKind::Captures {
+                moves.extend(castling::generate_castling_moves(piece, board));
+            }
+            moves
+        },
+        _ => vec![]
     };
 
     moves.into_iter().filter(|mv| {
         assert!(mv.is_valid());
-
-        // println!("mv: {:?}", mv);
-        !would_move_cause_check(board, *mv)
+        legality.is_legal(board, mv)
     }).collect::<Vec<Move>>()
 }
 
-
 pub fn is_color_checkmated(board: &Board, color: Color) -> bool {
-    let king = board.get_pieces_of_color(color).into_iter().find(|piece_pos| {
-        to_white(piece_pos.0) == KING
-    });
-
-    is_color_in_check(board, color) && 
-        generate_king_moves(king.unwrap().clone(), board, false).count() == 0
+    // blocks and captures of the checker count as well as king moves
+    is_color_in_check(board, color) && generate_all_moves_for_color(board, color).is_empty()
 }
 
 
@@ -173,51 +236,51 @@ fn moves_from_ray(piece: PiecePosition, squares: &[Position], board: &Board, as_
     moves
 }
 
-pub fn generate_bishop_moves(piece: PiecePosition, board: &Board, as_attacks: bool) -> MovesIter {
-    let mut diag = diag_squares(piece.to_position());
-    let mut anti_diag = anti_diag_squares(piece.to_position());
+// Turns a slider's attack set into moves.  Like `moves_from_ray`, squares holding a friendly
+// piece only count when generating attacks.
+fn moves_from_attack_set(piece: PiecePosition, attacks: u64, board: &Board, as_attacks: bool) -> Vec<Move> {
+    let mut moves: MoveList = Vec::with_capacity(attacks.count_ones() as usize);
+    let mut remaining = attacks;
+
+    while remaining != 0 {
+        let dest_pos = square_to_pos(remaining.trailing_zeros() as usize);
+        remaining &= remaining - 1;
 
-    let (pos_diag, neg_diag) = diag.squares.split_at_mut(diag.attacker_index as usize);
-    pos_diag.reverse();
+        let dest_piece = board.mb.getp(dest_pos);
 
-    let (pos_anti_diag, neg_anti_diag) = anti_diag.squares.split_at_mut(anti_diag.attacker_index as usize);
-    pos_anti_diag.reverse();
+        let meta_info = if dest_piece == NO_PIECE {
+            QUIET_MOVE
+        } else if !is_same_color(piece.0, dest_piece) || as_attacks {
+            CAPTURE
+        } else {
+            continue;
+        };
 
-    let mut moves = Vec::with_capacity(16);
-    moves.append(&mut moves_from_ray(piece, pos_diag, board, as_attacks));
-    moves.append(&mut moves_from_ray(piece, neg_diag, board, as_attacks));
+        moves.push(Move {
+            origin_piece: piece.0,
+            dest_piece,
+            origin_pos: piece.to_position(),
+            dest_pos,
+            meta_info
+        });
+    }
 
-    moves.append(&mut moves_from_ray(piece, pos_anti_diag, board, as_attacks));
-    moves.append(&mut moves_from_ray(piece, neg_anti_diag, board, as_attacks));
-    
-    MovesIter::from_vec(moves)
+    moves
+}
+
+pub fn generate_bishop_moves(piece: PiecePosition, board: &Board, as_attacks: bool) -> MovesIter {
+    let attacks = magics::bishop_attacks(square_index(piece.to_position()), board.bb[ALL]);
+    MovesIter::from_vec(moves_from_attack_set(piece, attacks, board, as_attacks))
 }
 
 pub fn generate_rook_moves(piece: PiecePosition, board: &Board, as_attacks: bool) -> MovesIter {
-    let mut hor = horizontal_squares(piece.to_position());
-    let mut vert = vertical_squares(piece.to_position());
-    
-    let (pos_hor, neg_hor) = hor.squares.split_at_mut(hor.attacker_index as usize);
-    let (pos_vert, neg_vert) = vert.squares.split_at_mut(vert.attacker_index as usize);
-    pos_hor.reverse();
-    pos_vert.reverse();
-
-    let mut moves = Vec::with_capacity(16);
-    moves.append(&mut moves_from_ray(piece, pos_hor, board, as_attacks));
-    moves.append(&mut moves_from_ray(piece, neg_hor, board, as_attacks));
-    moves.append(&mut moves_from_ray(piece, pos_vert, board, as_attacks));
-    moves.append(&mut moves_from_ray(piece, neg_vert, board, as_attacks));
-    
-    MovesIter::from_vec(moves)
+    let attacks = magics::rook_attacks(square_index(piece.to_position()), board.bb[ALL]);
+    MovesIter::from_vec(moves_from_attack_set(piece, attacks, board, as_attacks))
 }
 
 pub fn generate_queen_moves(piece: PiecePosition, board: &Board, as_attacks: bool) -> MovesIter {
-    let moves = iter::empty()
-        .chain(generate_bishop_moves(piece, board, as_attacks))
-        .chain(generate_rook_moves(piece, board, as_attacks))
-        .collect::<Vec<Move>>();
-
-    MovesIter::from_vec(moves)
+    let attacks = magics::queen_attacks(square_index(piece.to_position()), board.bb[ALL]);
+    MovesIter::from_vec(moves_from_attack_set(piece, attacks, board, as_attacks))
 }
 
 pub fn generate_knight_moves(piece: PiecePosition, board: &Board, as_attacks: bool) -> MovesIter {
@@ -634,3 +697,20 @@ pub fn get_piece_attacks(piece: PiecePosition, board: &Board) -> MovesIter {
     
     moves
 }
+
+// The same squares as `get_piece_attacks`, as a bitboard, for when all that matters is which
+// squares they are or how many, and building the moves would be wasted.
+pub fn get_piece_attack_set(piece: PiecePosition, board: &Board) -> u64 {
+    let square = square_index(piece.to_position());
+    let occupancy = board.bb[ALL];
+
+    match to_white(piece.0) {
+        PAWN => PAWN_ATTACKS[color_of(piece.0) as usize][square],
+        KNIGHT => KNIGHT_ATTACKS[square],
+        BISHOP => magics::bishop_attacks(square, occupancy),
+        ROOK => magics::rook_attacks(square, occupancy),
+        QUEEN => magics::queen_attacks(square, occupancy),
+        KING => KING_ATTACKS[square],
+        _ => 0,
+    }
+}
@@ -0,0 +1,166 @@
+// Legality without making the move.
+//
+// Once per position we find the pieces giving check, the friendly pieces pinned to the king (with
+// the ray each is allowed to move along) and the squares that resolve a check.  A non-king move
+// is then legal exactly when its destination is in the evasion mask and, for a pinned piece, on
+// its pin ray.  King moves and en passant captures still get a real test since they change which
+// squares are attacked, but that's a handful of table lookups against an adjusted occupancy.
+
+use board::Board;
+use bitboard::{ALL, ALL_WHITE, ALL_BLACK, square_index};
+use constants::*;
+use magics::{rook_attacks, bishop_attacks};
+use types::Position;
+use util::{to_white, to_color};
+
+use super::attacks::{KNIGHT_ATTACKS, KING_ATTACKS, PAWN_ATTACKS};
+use super::types::Move;
+
+pub struct LegalityInfo {
+    pub color: Color,
+    pub king_square: usize,
+    pub checkers: u64,
+    pub pinned: u64,
+    pub evasion_mask: u64,
+    pin_rays: [u64; 64],
+}
+
+impl LegalityInfo {
+    pub fn new(board: &Board, color: Color) -> Self {
+        let is_white = color == WHITE;
+        let bb = &board.bb;
+        let king = bb[to_color(W_KING, is_white)];
+        assert!(king != 0);
+
+        let king_square = king.trailing_zeros() as usize;
+        let occupancy = bb[ALL];
+        let own = bb[if is_white { ALL_WHITE } else { ALL_BLACK }];
+
+        let checkers = attackers_to(board, king_square, occupancy) & !own;
+
+        let mut info = LegalityInfo {
+            color,
+            king_square,
+            checkers,
+            pinned: 0,
+            evasion_mask: match checkers.count_ones() {
+                0 => !0,
+                1 => between(king_square, checkers.trailing_zeros() as usize) | checkers,
+                _ => 0,
+            },
+            pin_rays: [0; 64],
+        };
+
+        // enemy sliders that would see the king on an empty board, with exactly one piece between
+        let enemy_queens = bb[to_color(W_QUEEN, !is_white)];
+        let mut snipers = (rook_attacks(king_square, 0) & (bb[to_color(W_ROOK, !is_white)] | enemy_queens))
+            | (bishop_attacks(king_square, 0) & (bb[to_color(W_BISHOP, !is_white)] | enemy_queens));
+
+        while snipers != 0 {
+            let sniper = snipers.trailing_zeros() as usize;
+            snipers &= snipers - 1;
+
+            let blockers = between(king_square, sniper) & occupancy;
+            if blockers.count_ones() == 1 && blockers & own != 0 {
+                let pinned_square = blockers.trailing_zeros() as usize;
+                info.pinned |= blockers;
+                info.pin_rays[pinned_square] = between(king_square, sniper) | (1u64 << sniper);
+            }
+        }
+
+        info
+    }
+
+    pub fn is_in_check(&self) -> bool {
+        self.checkers != 0
+    }
+
+    // Is the pseudo-legal move (generated for `self.color`) legal?
+    pub fn is_legal(&self, board: &Board, mv: &Move) -> bool {
+        let origin = square_index(mv.origin_pos);
+        let dest = square_index(mv.dest_pos);
+
+        if to_white(mv.origin_piece) == W_KING {
+            // castling already checks that the king doesn't start in or pass through check
+            if mv.meta_info == KING_CASTLE || mv.meta_info == QUEEN_CASTLE {
+                return true;
+            }
+
+            // lift the king off so that a slider checking it also covers the squares behind it
+            let occupancy = board.bb[ALL] ^ (1u64 << origin);
+            return self.enemy_attackers(board, dest, occupancy) == 0;
+        }
+
+        if mv.meta_info == EP_CAPTURE {
+            let captured = square_index(Position(mv.dest_pos.0, mv.origin_pos.1));
+            let occupancy = (board.bb[ALL] ^ (1u64 << origin) ^ (1u64 << captured)) | (1u64 << dest);
+            return self.enemy_attackers(board, self.king_square, occupancy) & !(1u64 << captured) == 0;
+        }
+
+        if self.evasion_mask & (1u64 << dest) == 0 {
+            return false;
+        }
+
+        self.pinned & (1u64 << origin) == 0 || self.pin_rays[origin] & (1u64 << dest) != 0
+    }
+
+    fn enemy_attackers(&self, board: &Board, square: usize, occupancy: u64) -> u64 {
+        let enemy = board.bb[if self.color == WHITE { ALL_BLACK } else { ALL_WHITE }];
+        attackers_to(board, square, occupancy) & enemy
+    }
+}
+
+// Every piece of either color attacking the square, with sliders blocked by `occupancy`.
+pub fn attackers_to(board: &Board, square: usize, occupancy: u64) -> u64 {
+    let bb = &board.bb;
+    let rooks = bb[W_ROOK] | bb[B_ROOK] | bb[W_QUEEN] | bb[B_QUEEN];
+    let bishops = bb[W_BISHOP] | bb[B_BISHOP] | bb[W_QUEEN] | bb[B_QUEEN];
+
+    (PAWN_ATTACKS[BLACK as usize][square] & bb[W_PAWN])
+        | (PAWN_ATTACKS[WHITE as usize][square] & bb[B_PAWN])
+        | (KNIGHT_ATTACKS[square] & (bb[W_KNIGHT] | bb[B_KNIGHT]))
+        | (KING_ATTACKS[square] & (bb[W_KING] | bb[B_KING]))
+        | (rook_attacks(square, occupancy) & rooks)
+        | (bishop_attacks(square, occupancy) & bishops)
+}
+
+// The squares strictly between two squares on a line, or nothing if they aren't on one.
+pub fn between(a: usize, b: usize) -> u64 {
+    let (bit_a, bit_b) = (1u64 << a, 1u64 << b);
+
+    if rook_attacks(a, 0) & bit_b != 0 {
+        rook_attacks(a, bit_b) & rook_attacks(b, bit_a)
+    } else if bishop_attacks(a, 0) & bit_b != 0 {
+        bishop_attacks(a, bit_b) & bishop_attacks(b, bit_a)
+    } else {
+        0
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn finds_pins_and_checkers() {
+        // the e-file rook pins the knight on e4, the bishop on b4 checks the king
+        let board = Board::from_fen("4r2k/8/8/8/1b2N3/8/8/4K3 w - - 0 1");
+        let info = LegalityInfo::new(&board, WHITE);
+
+        assert_eq!(info.checkers, 1u64 << square_index(Position::from_pgn("b4")));
+        assert_eq!(info.pinned, 1u64 << square_index(Position::from_pgn("e4")));
+
+        // capturing the checker or blocking on c3/d2
+        let mask = [Position::from_pgn("b4"), Position::from_pgn("c3"), Position::from_pgn("d2")]
+            .iter().fold(0, |acc, pos| acc | 1u64 << square_index(*pos));
+        assert_eq!(info.evasion_mask, mask);
+    }
+
+    #[test]
+    fn between_squares() {
+        assert_eq!(between(0, 3), (1 << 1) | (1 << 2));
+        assert_eq!(between(0, 27), (1 << 9) | (1 << 18));
+        assert_eq!(between(0, 17), 0);
+        assert_eq!(between(0, 1), 0);
+    }
+}
@@ -1,11 +1,20 @@
+#![allow(dead_code)]
+#![allow(clippy::assertions_on_constants, clippy::assign_op_pattern, clippy::redundant_static_lifetimes)]
+
 use super::types::*;
 use util::*;
 use constants::*;
 use board::Board;
 use types::*;
 use board::history::MoveContext;
+use zobrist;
+use bitboard::BitBoard;
 
 pub fn make_move(board: &mut Board, mv: Move) {
+    if mv.meta_info == NULL_MOVE {
+        return make_null_move(board);
+    }
+
     assert!(mv.is_valid());
 
     //println!("board.to_move: {}", board.to_move);
@@ -15,6 +24,7 @@ pub fn make_move(board: &mut Board, mv: Move) {
     let move_context = MoveContext {
         pending_move: mv, 
         zhash: board.zhash,
+        pawn_hash: board.pawn_hash,
         castling: board.castling,
         en_passant: board.en_passant,
         halfmove_counter: board.halfmove_counter,
@@ -25,6 +35,16 @@ pub fn make_move(board: &mut Board, mv: Move) {
 
     let is_white: bool = board.to_move == WHITE;
 
+    let (touched, touched_count) = squares_touched(&mv);
+    let mut touched_pieces: [PieceType; 4] = [NO_PIECE; 4];
+    for i in 0..touched_count {
+        touched_pieces[i] = board.mb.getp(touched[i]);
+    }
+
+    let orig_castling = board.castling;
+    let orig_en_passant = board.en_passant;
+    let orig_to_move = board.to_move;
+
     match mv.meta_info {
         QUIET_MOVE => {
             board.mb.move_piece(mv.origin_pos, mv.dest_pos);
@@ -32,7 +52,7 @@ pub fn make_move(board: &mut Board, mv: Move) {
             if to_white(mv.origin_piece) == W_PAWN {
                 board.halfmove_counter = 0;
             } else {
-                board.halfmove_counter += 1;
+                board.halfmove_counter = board.halfmove_counter.saturating_add(1);
             }
         },
         DOUBLE_PAWN_PUSH => {
@@ -81,7 +101,7 @@ pub fn make_move(board: &mut Board, mv: Move) {
             
             // move the rook
             assert!(to_white(board.mb.get(0, mv.origin_pos.1)) == W_ROOK);
-            board.mb.move_piece(Position(0, mv.origin_pos.1), Position(KING_SIDE_CASTLE_FILE - 1, mv.origin_pos.1));
+            board.mb.move_piece(Position(0, mv.origin_pos.1), Position(QUEEN_SIDE_CASTLE_FILE + 1, mv.origin_pos.1));
             
             // update the castling flag
             let castle_mask = if is_white { W_OOO } else { B_OOO };
@@ -179,6 +199,19 @@ pub fn make_move(board: &mut Board, mv: Move) {
         board.castling = board.castling & !mask;
     }
 
+    // capturing a rook on its starting square takes away the other side's castling on that wing
+    let enemy_back_rank: Rank = if is_white { BLACK_BACK_RANK } else { WHITE_BACK_RANK };
+
+    if mv.dest_pos == Position(0, enemy_back_rank) {
+        let mask = if is_white { B_OOO } else { W_OOO };
+        board.castling &= !mask;
+    }
+
+    if mv.dest_pos == Position(FILE_COUNT - 1, enemy_back_rank) {
+        let mask = if is_white { B_OO } else { W_OO };
+        board.castling &= !mask;
+    }
+
     board.to_move = opposite_color(board.to_move);
     
     // if black just moved, increment
@@ -186,7 +219,114 @@ pub fn make_move(board: &mut Board, mv: Move) {
         board.fullmove_counter += 1;
     }
 
-    board.normalize();
+    // Only the squares that the move touched can change their contribution to the hash, so xor
+    // out the pieces that were there before and xor in what's there now.  Pawns go into the pawn
+    // hash as well.
+    let mut h = board.zhash;
+    let mut pawn_h = board.pawn_hash;
+    for i in 0..touched_count {
+        let (pos, before, after) = (touched[i], touched_pieces[i], board.mb.getp(touched[i]));
+
+        if before != NO_PIECE {
+            h ^= zobrist::get_piece_hash(before, pos.0, pos.1);
+        }
+
+        if after != NO_PIECE {
+            h ^= zobrist::get_piece_hash(after, pos.0, pos.1);
+        }
+
+        if before == W_PAWN || before == B_PAWN {
+            pawn_h ^= zobrist::get_piece_hash(before, pos.0, pos.1);
+        }
+
+        if after == W_PAWN || after == B_PAWN {
+            pawn_h ^= zobrist::get_piece_hash(after, pos.0, pos.1);
+        }
+    }
+    board.pawn_hash = pawn_h;
+
+    sync_bitboards(board, &touched[..touched_count], &touched_pieces[..touched_count]);
+
+    h ^= zobrist::get_castling_hash(orig_castling) ^ zobrist::get_castling_hash(board.castling);
+    h ^= zobrist::get_en_passant_hash(orig_en_passant) ^ zobrist::get_en_passant_hash(board.en_passant);
+    h ^= zobrist::get_to_move_hash(orig_to_move) ^ zobrist::get_to_move_hash(board.to_move);
+    board.zhash = h;
+
+    debug_assert_eq!(board.zhash, board.to_hash());
+    debug_assert_eq!(board.pawn_hash, board.to_pawn_hash());
+}
+
+// Passes the turn without moving anything, for null-move pruning and the uci "0000" move.  Only the
+// side to move and en passant change, so the hash is patched for those two.
+pub fn make_null_move(board: &mut Board) {
+    board.history.push(MoveContext {
+        pending_move: Move::NULL,
+        zhash: board.zhash,
+        pawn_hash: board.pawn_hash,
+        castling: board.castling,
+        en_passant: board.en_passant,
+        halfmove_counter: board.halfmove_counter,
+        fullmove_counter: board.fullmove_counter,
+    });
+
+    let orig_to_move = board.to_move;
+    board.to_move = opposite_color(orig_to_move);
+
+    let mut h = board.zhash;
+    h ^= zobrist::get_en_passant_hash(board.en_passant);
+    h ^= zobrist::get_to_move_hash(orig_to_move) ^ zobrist::get_to_move_hash(board.to_move);
+    board.zhash = h;
+
+    board.en_passant = NO_EN_PASSANT;
+    board.halfmove_counter = board.halfmove_counter.saturating_add(1);
+    if orig_to_move == BLACK {
+        board.fullmove_counter += 1;
+    }
+
+    debug_assert_eq!(board.zhash, board.to_hash());
+}
+
+// Brings the bitboards back in line with the mailbox after the pieces on `touched` were
+// `before`.  Squares holding the same piece as before are left alone.
+pub fn sync_bitboards(board: &mut Board, touched: &[Position], before: &[PieceType]) {
+    for (&pos, &before) in touched.iter().zip(before) {
+        let after = board.mb.getp(pos);
+
+        if before == after {
+            continue;
+        }
+
+        if before != NO_PIECE {
+            board.bb.toggle(before, pos);
+        }
+
+        if after != NO_PIECE {
+            board.bb.toggle(after, pos);
+        }
+    }
+
+    debug_assert!(board.bb == BitBoard::create_from(&board.mb));
+}
+
+// The squares whose contents are changed by a move.  Besides the origin and destination, that's
+// the captured pawn's square for en passant and the rook's squares for castling.
+pub fn squares_touched(mv: &Move) -> ([Position; 4], usize) {
+    let rank = mv.origin_pos.1;
+
+    match mv.meta_info {
+        EP_CAPTURE => {
+            ([mv.origin_pos, mv.dest_pos, Position(mv.dest_pos.0, rank), mv.origin_pos], 3)
+        },
+        KING_CASTLE => {
+            ([mv.origin_pos, mv.dest_pos, Position(FILE_COUNT - 1, rank), Position(KING_SIDE_CASTLE_FILE - 1, rank)], 4)
+        },
+        QUEEN_CASTLE => {
+            ([mv.origin_pos, mv.dest_pos, Position(0, rank), Position(QUEEN_SIDE_CASTLE_FILE + 1, rank)], 4)
+        },
+        _ => {
+            ([mv.origin_pos, mv.dest_pos, mv.origin_pos, mv.origin_pos], 2)
+        }
+    }
 }
 
 // Sort of randomized pieces
@@ -453,4 +593,42 @@ mod tests {
         assert_eq!(board.mb.getp(Position::from_pgn("b1")), B_QUEEN);
         assert_eq!(board.mb.getp(Position::from_pgn("b2")), NO_PIECE);
     }
+
+    #[test]
+    fn incremental_hash_matches_full_hash() {
+        // castling on both wings, a double push answered by an en passant capture, and a
+        // promotion that captures a rook.
+        let mut board = Board::from_fen("r3k2r/6p1/8/5P2/8/8/1p6/R3K2R b KQkq - 0 1");
+        let line = ["g7g5", "f5g6", "e8c8", "e1g1", "h8h1", "g1h1", "b2a1q"];
+
+        for pcn in line.iter() {
+            let mv = Move::from_pcn_string(pcn, &board);
+            make_move(&mut board, mv);
+            assert_eq!(board.zhash, board.to_hash(), "after {}", pcn);
+            assert_eq!(board.pawn_hash, board.to_pawn_hash(), "after {}", pcn);
+        }
+
+        assert_eq!(board.castling, 0);
+    }
+
+    #[test]
+    fn bitboards_follow_make_and_unmake() {
+        let mut board = Board::from_fen("r3k2r/6p1/8/5P2/8/8/1p6/R3K2R b KQkq - 0 1");
+        let orig_bb = board.bb;
+        let line = ["g7g5", "f5g6", "e8c8", "e1g1", "h8h1", "g1h1", "b2a1q"];
+        let mut played = vec![];
+
+        for pcn in line.iter() {
+            let mv = Move::from_pcn_string(pcn, &board);
+            make_move(&mut board, mv);
+            played.push(mv);
+            assert!(board.bb == BitBoard::create_from(&board.mb), "after {}", pcn);
+        }
+
+        for mv in played.iter().rev() {
+            ::moves::unmake_move::unmake_move(&mut board, mv);
+        }
+
+        assert!(board.bb == orig_bb);
+    }
 }
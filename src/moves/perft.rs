@@ -0,0 +1,46 @@
+use board::Board;
+use super::types::{Move};
+use super::generation::{generate_all_moves_for_color};
+use super::make_move::{make_move};
+use super::unmake_move::{unmake_move};
+
+// Counts the leaf nodes of the legal move tree `depth` plies deep.  The counts can be compared
+// against published numbers to verify the move generator.
+pub fn perft(board: &mut Board, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = generate_all_moves_for_color(board, board.to_move);
+
+    // bulk counting: the moves at the last ply don't need to be made
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes: u64 = 0;
+
+    for mv in &moves {
+        make_move(board, *mv);
+        nodes += perft(board, depth - 1);
+        unmake_move(board, mv);
+    }
+
+    nodes
+}
+
+// Like `perft`, but broken down by root move.  Diffing this against another engine's output is
+// the quickest way to find the move that the generator gets wrong.
+pub fn divide(board: &mut Board, depth: u8) -> Vec<(Move, u64)> {
+    assert!(depth > 0);
+
+    let moves = generate_all_moves_for_color(board, board.to_move);
+
+    moves.into_iter().map(|mv| {
+        make_move(board, mv);
+        let nodes = perft(board, depth - 1);
+        unmake_move(board, &mv);
+
+        (mv, nodes)
+    }).collect::<Vec<(Move, u64)>>()
+}
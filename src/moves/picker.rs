@@ -0,0 +1,287 @@
+// Hands out a position's legal moves a stage at a time, in the order they're most likely to cause
+// a cutoff:
+//
+//   1. the hash move
+//   2. captures and promotions that don't lose material, most valuable victim first and least
+//      valuable attacker after that
+//   3. the killers
+//   4. quiet moves, best history score first
+//   5. captures that lose material
+//
+// A stage is only generated once the ones before it have run out, so a node that cuts off on the
+// hash move or a good capture never generates its quiet moves at all.
+
+use board::Board;
+use constants::*;
+use eval::get_piece_value;
+use search::heuristics::Heuristics;
+use search::types::Score;
+use types::PiecePosition;
+use util::{to_white, color_of};
+
+use super::see::see_ge;
+use super::generation::{generate_captures, generate_quiets, generate_moves_for_piece};
+use super::types::Move;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Stage {
+    HashMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+// The board isn't held on to since the caller makes and unmakes moves between calls to `next`,
+// but it has to be back in the same position each time.
+pub struct MovePicker {
+    pub stage: Stage,
+    hash_move: Move,
+    killers: [Move; 2],
+
+    // the current stage's moves, best first, and how far through them we are
+    moves: Vec<(Move, i32)>,
+    index: usize,
+
+    bad_captures: Vec<(Move, i32)>,
+}
+
+// the piece a capture takes, as a white piece
+fn victim(mv: &Move) -> PieceType {
+    match mv.meta_info {
+        EP_CAPTURE => W_PAWN,
+        _ if mv.is_capture() => to_white(mv.dest_piece),
+        _ => NO_PIECE,
+    }
+}
+
+fn promotion_gain(mv: &Move) -> Score {
+    if mv.is_promotion() {
+        let piece = [W_KNIGHT, W_BISHOP, W_ROOK, W_QUEEN][(mv.meta_info & 0b11) as usize];
+        get_piece_value(piece) - get_piece_value(W_PAWN)
+    } else {
+        0
+    }
+}
+
+// Most valuable victim, least valuable attacker.  Victims are weighted by more than the king is
+// worth so that the attacker only ever breaks ties.
+pub fn mvv_lva(mv: &Move) -> i32 {
+    let gain = get_piece_value(victim(mv)) + promotion_gain(mv);
+    gain as i32 * 128 - get_piece_value(to_white(mv.origin_piece)) as i32
+}
+
+// Moves from the table or from another node might not even be pseudo-legal here.
+fn is_legal_in(board: &Board, mv: &Move) -> bool {
+    *mv != Move::NULL
+        && color_of(mv.origin_piece) == board.to_move
+        && board.mb.getp(mv.origin_pos) == mv.origin_piece
+        && generate_moves_for_piece(PiecePosition(mv.origin_piece, mv.origin_pos.0, mv.origin_pos.1), board).contains(mv)
+}
+
+impl MovePicker {
+    // Either move can be `Move::NULL` and the killers don't need to be legal here, they get
+    // checked before being handed out.
+    pub fn new(hash_move: Move, killers: [Move; 2]) -> Self {
+        MovePicker {
+            stage: Stage::HashMove,
+            hash_move,
+            killers,
+            moves: vec![],
+            index: 0,
+            bad_captures: vec![],
+        }
+    }
+
+    // already handed out by an earlier stage
+    fn is_repeat(&self, mv: &Move) -> bool {
+        *mv == self.hash_move || (self.stage > Stage::Killers && self.killers.contains(mv))
+    }
+
+    fn set_moves(&mut self, mut moves: Vec<(Move, i32)>) {
+        // stable, so that equal moves keep the order they were generated in
+        moves.sort_by_key(|&(_, score)| -score);
+        self.moves = moves;
+        self.index = 0;
+    }
+
+    fn next_in_stage(&mut self) -> Option<Move> {
+        let mv = self.moves.get(self.index).map(|&(mv, _)| mv);
+        self.index += 1;
+        mv
+    }
+
+    // The heuristics are only borrowed for the call, since the search updates them between calls.
+    pub fn next(&mut self, board: &Board, heuristics: &Heuristics) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    if is_legal_in(board, &self.hash_move) {
+                        return Some(self.hash_move);
+                    }
+                },
+
+                Stage::GenerateCaptures => {
+                    let mut good = vec![];
+                    for mv in generate_captures(board, board.to_move) {
+                        if self.is_repeat(&mv) {
+                            continue;
+                        }
+
+                        if see_ge(board, &mv, 0) {
+                            good.push((mv, mvv_lva(&mv)));
+                        } else {
+                            self.bad_captures.push((mv, mvv_lva(&mv)));
+                        }
+                    }
+
+                    self.set_moves(good);
+                    self.stage = Stage::GoodCaptures;
+                },
+
+                Stage::GoodCaptures => {
+                    match self.next_in_stage() {
+                        Some(mv) => return Some(mv),
+                        None => {
+                            self.stage = Stage::Killers;
+                            self.index = 0;
+                        }
+                    }
+                },
+
+                Stage::Killers => {
+                    if self.index >= self.killers.len() {
+                        self.stage = Stage::GenerateQuiets;
+                        continue;
+                    }
+
+                    let killer = self.killers[self.index];
+                    self.index += 1;
+
+                    // a killer that captures here was already handed out with the captures
+                    let is_new = killer != self.hash_move && !self.killers[..self.index - 1].contains(&killer);
+                    if is_new && !killer.is_capture() && !killer.is_promotion() && is_legal_in(board, &killer) {
+                        return Some(killer);
+                    }
+                },
+
+                Stage::GenerateQuiets => {
+                    // leave this stage first so that `is_repeat` skips the killers too
+                    self.stage = Stage::Quiets;
+
+                    let quiets = generate_quiets(board, board.to_move).into_iter()
+                        .filter(|mv| !self.is_repeat(mv))
+                        .map(|mv| (mv, heuristics.history(&mv)))
+                        .collect::<Vec<(Move, i32)>>();
+
+                    self.set_moves(quiets);
+                },
+
+                Stage::Quiets => {
+                    match self.next_in_stage() {
+                        Some(mv) => return Some(mv),
+                        None => {
+                            let bad_captures = self.bad_captures.drain(..).collect::<Vec<(Move, i32)>>();
+                            self.set_moves(bad_captures);
+                            self.stage = Stage::BadCaptures;
+                        }
+                    }
+                },
+
+                Stage::BadCaptures => {
+                    match self.next_in_stage() {
+                        Some(mv) => return Some(mv),
+                        None => self.stage = Stage::Done,
+                    }
+                },
+
+                Stage::Done => return None,
+            }
+        }
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[allow(dead_code)]
+    fn picked(board: &Board, hash_move: Move, killers: [Move; 2]) -> Vec<Move> {
+        let mut picker = MovePicker::new(hash_move, killers);
+        let mut moves = vec![];
+        let heuristics = Heuristics::new();
+        while let Some(mv) = picker.next(board, &heuristics) {
+            moves.push(mv);
+        }
+        moves
+    }
+
+    #[test]
+    fn picks_every_legal_move_once() {
+        use moves::generation::generate_all_moves_for_color;
+
+        let board = Board::from_fen(KIWIPETE);
+        let hash_move = Move::from_pcn_string("e2a6", &board);
+        let killers = [Move::from_pcn_string("a2a3", &board), Move::from_pcn_string("a2a3", &board)];
+
+        let mut moves = picked(&board, hash_move, killers);
+        let mut all = generate_all_moves_for_color(&board, WHITE);
+        assert_eq!(moves[0], hash_move);
+
+        moves.sort_by_key(|mv| mv.to_pcn_string());
+        all.sort_by_key(|mv| mv.to_pcn_string());
+        assert_eq!(moves, all);
+    }
+
+    #[test]
+    fn stages_come_in_order() {
+        // the queen can take a defended pawn on d5 or a loose rook on a5, the knight a loose pawn
+        let board = Board::from_fen("4k3/8/2p2p2/r2p4/4N3/8/3Q4/4K3 w - - 0 1");
+        let killer = Move::from_pcn_string("e1f1", &board);
+        let moves = picked(&board, Move::NULL, [killer, Move::NULL]);
+        let pcns = moves.iter().map(|mv| mv.to_pcn_string()).collect::<Vec<String>>();
+
+        assert_eq!(pcns[0], "d2a5");
+        assert_eq!(pcns[1], "e4f6");
+        assert_eq!(pcns[2], "e1f1");
+        assert!(!pcns[3..pcns.len() - 1].iter().any(|pcn| pcn == "d2d5"));
+        assert_eq!(pcns[pcns.len() - 1], "d2d5");
+    }
+
+    #[test]
+    fn quiets_come_in_history_order() {
+        let board = Board::from_fen("4k3/8/8/8/8/2N5/8/4K3 w - - 0 1");
+        let good = Move::from_pcn_string("c3b5", &board);
+        let better = Move::from_pcn_string("e1d2", &board);
+
+        let mut heuristics = Heuristics::new();
+        heuristics.record_cutoff(&good, 0, 2, &[]);
+        heuristics.record_cutoff(&better, 0, 3, &[]);
+
+        let mut picker = MovePicker::new(Move::NULL, [Move::NULL; 2]);
+        assert_eq!(picker.next(&board, &heuristics), Some(better));
+        assert_eq!(picker.next(&board, &heuristics), Some(good));
+    }
+
+    #[test]
+    fn skips_moves_that_dont_fit_the_position() {
+        let board = Board::from_fen(KIWIPETE);
+        let other = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+
+        // a table collision or a killer from another branch
+        let hash_move = Move::from_pcn_string("a1a8", &other);
+        let killer = Move::from_pcn_string("a1a5", &other);
+
+        let moves = picked(&board, hash_move, [killer, Move::NULL]);
+        assert!(!moves.contains(&hash_move));
+        assert!(!moves.contains(&killer));
+        assert_eq!(moves.len(), 48);
+    }
+}
@@ -0,0 +1,204 @@
+// Standard algebraic notation, eg. Nbd7, exd6, e8=Q+ or O-O-O#.
+
+use std::error::Error;
+use std::fmt;
+
+use board::Board;
+use constants::*;
+use types::Position;
+use util::*;
+
+use super::generation::generate_all_moves_for_color;
+use super::make_move::make_move;
+use super::is_color_in_check;
+use super::types::{Move, MetaInfo};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    // not shaped like a SAN move at all
+    Malformed(String),
+
+    // well formed, but no legal move fits it
+    NoMatch(String),
+
+    // more than one legal move fits it, eg. "Nd2" when both knights can get there
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SanError::Malformed(ref s) => write!(f, "malformed move '{}'", s),
+            SanError::NoMatch(ref s) => write!(f, "no legal move matches '{}'", s),
+            SanError::Ambiguous(ref s) => write!(f, "'{}' is ambiguous", s),
+        }
+    }
+}
+
+impl Error for SanError {}
+
+// the white piece a promotion produces
+fn promotion_piece(meta_info: MetaInfo) -> PieceType {
+    [W_KNIGHT, W_BISHOP, W_ROOK, W_QUEEN][(meta_info & 0b11) as usize]
+}
+
+fn square_to_string(pos: Position) -> String {
+    format!("{}{}", file_to_char(pos.0), pos.1 + 1)
+}
+
+impl Move {
+    // `board` is the position before the move is made.
+    pub fn to_san(&self, board: &Board) -> String {
+        let mut s = match self.meta_info {
+            KING_CASTLE => "O-O".to_string(),
+            QUEEN_CASTLE => "O-O-O".to_string(),
+            _ => self.san_body(board),
+        };
+
+        let mut after = board.clone();
+        make_move(&mut after, *self);
+
+        let opponent = after.to_move;
+        if is_color_in_check(&after, opponent) {
+            let is_mate = generate_all_moves_for_color(&after, opponent).is_empty();
+            s.push(if is_mate { '#' } else { '+' });
+        }
+
+        s
+    }
+
+    fn san_body(&self, board: &Board) -> String {
+        let mut s = String::new();
+        let is_pawn = to_white(self.origin_piece) == W_PAWN;
+
+        if is_pawn {
+            if self.is_capture() {
+                s.push_str(file_to_char(self.origin_pos.0));
+            }
+        } else {
+            s.push(piece_type_to_char(to_white(self.origin_piece)));
+
+            // other pieces of the same kind that can also reach the destination
+            let rivals = generate_all_moves_for_color(board, color_of(self.origin_piece)).into_iter().filter(|mv| {
+                mv.origin_piece == self.origin_piece && mv.dest_pos == self.dest_pos && mv.origin_pos != self.origin_pos
+            }).collect::<Vec<Move>>();
+
+            if !rivals.is_empty() {
+                let shares_file = rivals.iter().any(|mv| mv.origin_pos.0 == self.origin_pos.0);
+                let shares_rank = rivals.iter().any(|mv| mv.origin_pos.1 == self.origin_pos.1);
+
+                if !shares_file {
+                    s.push_str(file_to_char(self.origin_pos.0));
+                } else if !shares_rank {
+                    s.push_str(&format!("{}", self.origin_pos.1 + 1));
+                } else {
+                    s.push_str(&square_to_string(self.origin_pos));
+                }
+            }
+        }
+
+        if self.is_capture() {
+            s.push('x');
+        }
+
+        s.push_str(&square_to_string(self.dest_pos));
+
+        if self.is_promotion() {
+            s.push('=');
+            s.push(piece_type_to_char(promotion_piece(self.meta_info)));
+        }
+
+        s
+    }
+
+    // Accepts the usual variations: check and annotation suffixes, zeros for castling, and
+    // promotions with or without the `=`.  A capture doesn't need its `x`.
+    pub fn from_san(san: &str, board: &Board) -> Result<Move, SanError> {
+        let malformed = || SanError::Malformed(san.to_string());
+
+        let trimmed = san.trim().trim_end_matches(|c| "+#!?".contains(c));
+        let legal = generate_all_moves_for_color(board, board.to_move);
+
+        let castle = match trimmed {
+            "O-O" | "0-0" => Some(KING_CASTLE),
+            "O-O-O" | "0-0-0" => Some(QUEEN_CASTLE),
+            _ => None
+        };
+
+        if let Some(meta_info) = castle {
+            return legal.into_iter().find(|mv| mv.meta_info == meta_info)
+                .ok_or_else(|| SanError::NoMatch(san.to_string()));
+        }
+
+        let mut chars = trimmed.chars().collect::<Vec<char>>();
+
+        let mut promotion: Option<PieceType> = None;
+        if let Some(&last) = chars.last() {
+            if "NBRQ".contains(last) {
+                promotion = Some(to_white(char_to_piece_type(&last)));
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+
+        let piece = match chars.first() {
+            Some(&c) if "NBRQK".contains(c) => {
+                chars.remove(0);
+                to_white(char_to_piece_type(&c))
+            },
+            Some(_) => W_PAWN,
+            None => return Err(malformed()),
+        };
+
+        // the destination is always the last two characters
+        if chars.len() < 2 {
+            return Err(malformed());
+        }
+
+        let dest = parse_square(chars[chars.len() - 2], chars[chars.len() - 1]).ok_or_else(&malformed)?;
+        chars.truncate(chars.len() - 2);
+
+        if chars.last() == Some(&'x') {
+            chars.pop();
+        }
+
+        // whatever is left disambiguates the origin
+        let mut from_file: Option<File> = None;
+        let mut from_rank: Option<Rank> = None;
+        for c in chars {
+            match c {
+                'a'..='h' if from_file.is_none() => from_file = Some((c as u8 - b'a') as File),
+                '1'..='8' if from_rank.is_none() => from_rank = Some((c as u8 - b'1') as Rank),
+                _ => return Err(malformed()),
+            }
+        }
+
+        let mut matches = legal.into_iter().filter(|mv| {
+            to_white(mv.origin_piece) == piece
+                && mv.meta_info != KING_CASTLE
+                && mv.meta_info != QUEEN_CASTLE
+                && mv.dest_pos == dest
+                && from_file.is_none_or(|f| mv.origin_pos.0 == f)
+                && from_rank.is_none_or(|r| mv.origin_pos.1 == r)
+                && match promotion {
+                    Some(p) => mv.is_promotion() && promotion_piece(mv.meta_info) == p,
+                    None => !mv.is_promotion(),
+                }
+        });
+
+        match (matches.next(), matches.next()) {
+            (Some(mv), None) => Ok(mv),
+            (None, _) => Err(SanError::NoMatch(san.to_string())),
+            (Some(_), Some(_)) => Err(SanError::Ambiguous(san.to_string())),
+        }
+    }
+}
+
+fn parse_square(file: char, rank: char) -> Option<Position> {
+    match (file, rank) {
+        ('a'..='h', '1'..='8') => Some(Position((file as u8 - b'a') as File, (rank as u8 - b'1') as Rank)),
+        _ => None
+    }
+}
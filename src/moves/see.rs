@@ -0,0 +1,243 @@
+// Static exchange evaluation: what a capture wins or loses once every piece that can join in on
+// the destination square has, cheapest first.  Either side can stop capturing whenever carrying
+// on would cost it material.
+//
+// Attackers are recomputed against an occupancy that drops each piece as it captures, which
+// brings in the x-ray attackers lined up behind sliders (and pawns) for free.
+
+use board::Board;
+use bitboard::{ALL, ALL_WHITE, ALL_BLACK, square_index};
+use constants::*;
+use eval::get_piece_value;
+use search::types::Score;
+use types::Position;
+use util::{to_white, to_color, color_of, opposite_color};
+
+use super::legal::attackers_to;
+use super::types::Move;
+
+// cheapest first, which is the order pieces join the exchange in
+const EXCHANGE_ORDER: [PieceType; 6] = [W_PAWN, W_KNIGHT, W_BISHOP, W_ROOK, W_QUEEN, W_KING];
+
+// the most captures there can be in one exchange, with every piece of both sides on one square
+const MAX_EXCHANGE: usize = 32;
+
+fn value(piece: PieceType) -> Score {
+    get_piece_value(to_white(piece))
+}
+
+// The piece left standing on the destination, as a white piece, and what moving there wins
+// before anyone recaptures.
+fn first_capture(mv: &Move) -> (PieceType, Score) {
+    let captured = match mv.meta_info {
+        EP_CAPTURE => W_PAWN,
+        _ if mv.is_capture() => to_white(mv.dest_piece),
+        _ => NO_PIECE,
+    };
+
+    if mv.is_promotion() {
+        let promoted = [W_KNIGHT, W_BISHOP, W_ROOK, W_QUEEN][(mv.meta_info & 0b11) as usize];
+        (promoted, value(captured) + value(promoted) - value(W_PAWN))
+    } else {
+        (to_white(mv.origin_piece), value(captured))
+    }
+}
+
+// The occupancy just after the move, with the pawn taken en passant gone too.
+fn occupancy_after(board: &Board, mv: &Move) -> u64 {
+    let mut occupancy = board.bb[ALL] ^ (1u64 << square_index(mv.origin_pos));
+    occupancy |= 1u64 << square_index(mv.dest_pos);
+
+    if mv.meta_info == EP_CAPTURE {
+        occupancy ^= 1u64 << square_index(Position(mv.dest_pos.0, mv.origin_pos.1));
+    }
+
+    occupancy
+}
+
+fn side_pieces(board: &Board, color: Color) -> u64 {
+    board.bb[if color == WHITE { ALL_WHITE } else { ALL_BLACK }]
+}
+
+// The cheapest of `color`'s pieces in `attackers`, as a white piece, and its square.
+fn least_valuable(board: &Board, attackers: u64, color: Color) -> Option<(PieceType, usize)> {
+    EXCHANGE_ORDER.iter().filter_map(|&piece| {
+        let of_kind = attackers & board.bb[to_color(piece, color == WHITE)];
+        if of_kind == 0 {
+            None
+        } else {
+            Some((piece, of_kind.trailing_zeros() as usize))
+        }
+    }).next()
+}
+
+// The material `mv` wins for the side making it, from the point of view of that side.  A king only
+// joins the exchange if the other side has nothing left to take it with.
+pub fn see(board: &Board, mv: &Move) -> Score {
+    let target = square_index(mv.dest_pos);
+    let mut occupancy = occupancy_after(board, mv);
+    let mut color = opposite_color(color_of(mv.origin_piece));
+
+    let (mut on_square, first_gain) = first_capture(mv);
+
+    // gains[d] is what the side making capture d has won so far, if the exchange stops there
+    let mut gains: [Score; MAX_EXCHANGE] = [0; MAX_EXCHANGE];
+    gains[0] = first_gain;
+    let mut d = 0;
+
+    loop {
+        let attackers = attackers_to(board, target, occupancy) & occupancy;
+        let (piece, square) = match least_valuable(board, attackers & side_pieces(board, color), color) {
+            Some(attacker) => attacker,
+            None => break,
+        };
+
+        if piece == W_KING && attackers & side_pieces(board, opposite_color(color)) != 0 {
+            break;
+        }
+
+        d += 1;
+        gains[d] = value(on_square) - gains[d - 1];
+
+        on_square = piece;
+        occupancy ^= 1u64 << square;
+        color = opposite_color(color);
+
+        if d + 1 == MAX_EXCHANGE {
+            break;
+        }
+    }
+
+    // each side only makes its capture if that's better than stopping before it
+    while d > 0 {
+        gains[d - 1] = -Score::max(-gains[d - 1], gains[d]);
+        d -= 1;
+    }
+
+    gains[0]
+}
+
+// Does `mv` win at least `threshold`?  Gives the same answer as `see(board, mv) >= threshold`, but
+// stops as soon as the answer is known instead of playing out the whole exchange.
+pub fn see_ge(board: &Board, mv: &Move, threshold: Score) -> bool {
+    let (on_square, first_gain) = first_capture(mv);
+
+    // how far the side to capture next would be past the threshold if it gave up now, negated
+    // whenever it's the other side's turn
+    let mut swap = first_gain - threshold;
+    if swap < 0 {
+        return false;
+    }
+
+    // even losing the piece that moved keeps us at the threshold
+    swap = value(on_square) - swap;
+    if swap <= 0 {
+        return true;
+    }
+
+    let target = square_index(mv.dest_pos);
+    let mut occupancy = occupancy_after(board, mv);
+    let mut color = color_of(mv.origin_piece);
+
+    // whether the side that moved comes out ahead if the exchange stops here
+    let mut result = true;
+
+    loop {
+        color = opposite_color(color);
+
+        let attackers = attackers_to(board, target, occupancy) & occupancy;
+        let (piece, square) = match least_valuable(board, attackers & side_pieces(board, color), color) {
+            Some(attacker) => attacker,
+            None => break,
+        };
+
+        result = !result;
+
+        // a king can't capture into a square that's still defended
+        if piece == W_KING {
+            let defended = attackers & side_pieces(board, opposite_color(color)) != 0;
+            return if defended { !result } else { result };
+        }
+
+        swap = value(piece) - swap;
+        if swap < result as Score {
+            break;
+        }
+
+        occupancy ^= 1u64 << square;
+    }
+
+    result
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use moves::generation::generate_all_moves_for_color;
+
+    #[allow(dead_code)]
+    fn see_for(fen: &str, pcn: &str) -> Score {
+        let board = Board::from_fen(fen);
+        see(&board, &Move::from_pcn_string(pcn, &board))
+    }
+
+    #[test]
+    fn simple_exchanges() {
+        // a loose pawn, then the same pawn defended and taken by the queen
+        assert_eq!(see_for("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), 100);
+        assert_eq!(see_for("4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1", "d2d5"), -900);
+
+        // knight for knight
+        assert_eq!(see_for("4k3/8/4p3/3n4/8/4N3/8/4K3 w - - 0 1", "e3d5"), 0);
+
+        // a quiet move onto a square the pawn covers
+        assert_eq!(see_for("4k3/8/8/4p3/8/8/8/2B1K3 w - - 0 1", "c1f4"), -300);
+    }
+
+    #[test]
+    fn x_ray_attackers_join_in() {
+        // the white queen backs up the rook on the e-file, the black queen the bishop on the long
+        // diagonal: NxP NxN RxN BxR QxB QxQ
+        assert_eq!(see_for("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5"), -200);
+    }
+
+    #[test]
+    fn special_moves() {
+        assert_eq!(see_for("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 100);
+        assert_eq!(see_for("4k3/2p5/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 0);
+
+        // promoting wins a queen for a pawn, unless the rook takes it straight back
+        assert_eq!(see_for("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q"), 900);
+        assert_eq!(see_for("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q"), -100);
+        assert_eq!(see_for("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7a8q"), 1400);
+
+        // the king only recaptures when the square isn't defended
+        assert_eq!(see_for("3k4/4r3/8/8/8/8/4P3/4K3 b - - 0 1", "e7e2"), -400);
+        assert_eq!(see_for("3k4/4r3/8/1b6/8/8/4P3/4K3 b - - 0 1", "e7e2"), 100);
+    }
+
+    #[test]
+    fn see_ge_agrees_with_see() {
+        let fens = [
+            "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "3k4/4r3/8/1b6/8/8/4P3/4K3 b - - 0 1",
+            "4k3/2p5/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        ];
+
+        for fen in fens.iter() {
+            let board = Board::from_fen(fen);
+
+            for mv in generate_all_moves_for_color(&board, board.to_move) {
+                let score = see(&board, &mv);
+
+                for threshold in (-1500..1600).step_by(50) {
+                    assert_eq!(see_ge(&board, &mv, threshold), score >= threshold,
+                        "{} >= {} for {} in {}", score, threshold, mv.to_pcn_string(), fen);
+                }
+            }
+        }
+    }
+}
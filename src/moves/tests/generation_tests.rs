@@ -1,14 +1,17 @@
-const TEST_FEN1: &'static str = "r1bk1b1K/pp2p1p1/N1p1Pq1B/2B1rp2/Rn1P1PQP/1p1n1R2/P1P1P1P1/1N6 w - - 6 1";
-const BISHOP_TEST_FEN: &'static str = "7k/8/8/1p3P2/8/3B4/8/K7 w - - 0 1";
-const ROOK_TEST_FEN: &'static str = "7k/8/8/1p3P2/8/8/5r1p/K7 b - - 0 1";
-const QUEEN_TEST_FEN: &'static str = "7k/8/8/1p3P2/8/8/5Q1p/K7 w - - 0 1";
-const KING_TEST_FEN: &'static str = "7k/8/8/1p3P2/8/1Pp1r3/2K1b2p/8 w - - 0 1";
-const KING_CHECKED_TEST_FEN: &'static str = "7k/8/8/1p3P2/8/1Pp5/2K1r2p/8 w - - 0 1";
-const KNIGHT_TEST_FEN: &'static str = "7k/8/8/3b1P2/8/4n3/8/K7 b - - 0 1";
-const PAWN_TEST_FEN: &'static str = "7k/8/8/1p3P2/1bn5/1Pp5/2K1r2p/8 w - - 0 1";
-
-const ONLY_VALID_MOVE_KC5: &'static str = "8/8/7r/3K4/7r/8/7k/4r3 w - - 0 1";
-const ONLY_VALID_MOVE_NXC5: &'static str = "5b2/3N4/7r/2rK4/7r/8/7k/4r3 w - - 0 1";
+#![allow(unused_mut)]
+#![allow(clippy::bool_assert_comparison, clippy::len_zero)]
+
+const TEST_FEN1: &str = "r1bk1b1K/pp2p1p1/N1p1Pq1B/2B1rp2/Rn1P1PQP/1p1n1R2/P1P1P1P1/1N6 w - - 6 1";
+const BISHOP_TEST_FEN: &str = "7k/8/8/1p3P2/8/3B4/8/K7 w - - 0 1";
+const ROOK_TEST_FEN: &str = "7k/8/8/1p3P2/8/8/5r1p/K7 b - - 0 1";
+const QUEEN_TEST_FEN: &str = "7k/8/8/1p3P2/8/8/5Q1p/K7 w - - 0 1";
+const KING_TEST_FEN: &str = "7k/8/8/1p3P2/8/1Pp1r3/2K1b2p/8 w - - 0 1";
+const KING_CHECKED_TEST_FEN: &str = "7k/8/8/1p3P2/8/1Pp5/2K1r2p/8 w - - 0 1";
+const KNIGHT_TEST_FEN: &str = "7k/8/8/3b1P2/8/4n3/8/K7 b - - 0 1";
+const PAWN_TEST_FEN: &str = "7k/8/8/1p3P2/1bn5/1Pp5/2K1r2p/8 w - - 0 1";
+
+const ONLY_VALID_MOVE_KC5: &str = "8/8/7r/3K4/7r/8/7k/4r3 w - - 0 1";
+const ONLY_VALID_MOVE_NXC5: &str = "5b2/3N4/7r/2rK4/7r/8/7k/4r3 w - - 0 1";
 
 use constants::*;
 use types::Position;
@@ -442,3 +445,17 @@ fn test_diag_squares() {
     ]);
     assert_eq!(ray.attacker_index, 2);
 }
+
+#[test]
+fn attack_sets_match_generated_attacks() {
+    for fen in [TEST_FEN1, PAWN_TEST_FEN, KING_TEST_FEN, "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"].iter() {
+        let board = Board::from_fen(fen);
+
+        for piece in board.get_pieces() {
+            let generated = get_piece_attacks(piece, &board)
+                .fold(0u64, |set, mv| set | 1 << (mv.dest_pos.1 * 8 + mv.dest_pos.0));
+
+            assert_eq!(get_piece_attack_set(piece, &board), generated, "{:?} in {}", piece, fen);
+        }
+    }
+}
@@ -1,5 +1,12 @@
+// Only the test harness calls into these modules, so outside `cargo test` all of it is unused.
+#![allow(dead_code, unused_imports)]
+#![allow(unused_parens, unused_variables)]
+#![allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
+
 mod generation_tests;
 mod random_moves;
+mod perft_tests;
+mod san_tests;
 
 #[allow(unused_imports)]
 use super::*;
@@ -8,6 +15,7 @@ use super::*;
 use util::*;
 
 use moves::generation::{is_color_checkmated};
+use types::{Position, PiecePosition};
 
 
 #[test]        
@@ -164,3 +172,20 @@ fn test_is_color_in_check() {
 }
 
 
+
+#[test]
+fn test_pcn_round_trip() {
+    let board = Board::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+
+    for pcn in &["e1g1", "e1c1", "a1a8", "e5d6", "b7b8q", "b7a8n", "e5e6"] {
+        let mv = Move::from_pcn_string(pcn, &board);
+        assert_eq!(&mv.to_pcn_string(), pcn);
+    }
+
+    assert_eq!(Move::from_pcn_string("e1g1", &board).meta_info, KING_CASTLE);
+    assert_eq!(Move::from_pcn_string("e1c1", &board).meta_info, QUEEN_CASTLE);
+    assert_eq!(Move::from_pcn_string("a1a8", &board).meta_info, CAPTURE);
+    assert_eq!(Move::from_pcn_string("e5d6", &board).meta_info, EP_CAPTURE);
+    assert_eq!(Move::from_pcn_string("b7a8n", &board).meta_info, KNIGHT_PROMO_CAPTURE);
+    assert_eq!(Move::from_pcn_string("e5e6", &board).meta_info, QUIET_MOVE);
+}
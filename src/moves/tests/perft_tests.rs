@@ -0,0 +1,148 @@
+use board::Board;
+use constants::*;
+use moves::{perft, divide, would_move_cause_check};
+use moves::generation::*;
+use moves::legal::LegalityInfo;
+use moves::types::Move;
+use util::to_white;
+use moves::make_move::make_move;
+use moves::unmake_move::unmake_move;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+// Lots of en passant captures, some of which expose the king along the rank.
+const EP_PINS: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+
+// Promotions, promo captures and castling out of check.
+const PROMOTIONS: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const PROMOTIONS_MIRRORED: &str = "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+
+const DISCOVERED_CHECKS: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const MIDDLEGAME: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+fn assert_perft(fen: &str, counts: &[u64]) {
+    let mut board = Board::from_fen(fen);
+
+    for (i, count) in counts.iter().enumerate() {
+        let depth = (i + 1) as u8;
+        assert_eq!(perft(&mut board, depth), *count, "perft({}) of {}", depth, fen);
+    }
+
+    assert_eq!(board.to_fen(), Board::from_fen(fen).to_fen());
+}
+
+#[test]
+fn perft_start_position() {
+    assert_perft(START_FEN, &[20, 400, 8902]);
+}
+
+#[test]
+fn perft_kiwipete() {
+    assert_perft(KIWIPETE, &[48, 2039, 97862]);
+}
+
+#[test]
+fn perft_en_passant_pins() {
+    assert_perft(EP_PINS, &[14, 191, 2812, 43238]);
+}
+
+#[test]
+fn perft_promotions() {
+    assert_perft(PROMOTIONS, &[6, 264, 9467]);
+    assert_perft(PROMOTIONS_MIRRORED, &[6, 264, 9467]);
+}
+
+#[test]
+fn perft_discovered_checks() {
+    assert_perft(DISCOVERED_CHECKS, &[44, 1486, 62379]);
+}
+
+#[test]
+fn perft_middlegame() {
+    assert_perft(MIDDLEGAME, &[46, 2079, 89890]);
+}
+
+#[test]
+fn divide_sums_to_perft() {
+    let mut board = Board::from_fen(KIWIPETE);
+    let by_move = divide(&mut board, 2);
+
+    assert_eq!(by_move.len(), 48);
+    assert_eq!(by_move.iter().map(|&(_, nodes)| nodes).sum::<u64>(), 2039);
+}
+
+// Walks the tree checking that captures and quiets split the legal moves between them.
+fn assert_kinds_partition(board: &mut Board, depth: u8) {
+    let color = board.to_move;
+    let all = generate_all_moves_for_color(board, color);
+    let captures = generate_captures(board, color);
+    let quiets = generate_quiets(board, color);
+
+    assert_eq!(captures.len() + quiets.len(), all.len(), "{}", board.to_fen());
+    assert!(captures.iter().all(|mv| (mv.is_capture() || mv.is_promotion()) && all.contains(mv)), "{}", board.to_fen());
+    assert!(quiets.iter().all(|mv| !mv.is_capture() && !mv.is_promotion() && all.contains(mv)), "{}", board.to_fen());
+
+    if depth > 1 {
+        for mv in all.iter() {
+            make_move(board, *mv);
+            assert_kinds_partition(board, depth - 1);
+            unmake_move(board, mv);
+        }
+    }
+}
+
+#[test]
+fn captures_and_quiets_partition_moves() {
+    for fen in [KIWIPETE, EP_PINS, PROMOTIONS, PROMOTIONS_MIRRORED, DISCOVERED_CHECKS].iter() {
+        let mut board = Board::from_fen(fen);
+        assert_kinds_partition(&mut board, 3);
+    }
+}
+
+// Every move each piece could make, checked or not.
+fn candidate_moves(board: &Board) -> Vec<Move> {
+    board.get_pieces_of_color(board.to_move).into_iter().flat_map(|piece| {
+        match to_white(piece.0) {
+            PAWN => generate_pawn_moves(piece, board, true, false),
+            KNIGHT => generate_knight_moves(piece, board, false),
+            BISHOP => generate_bishop_moves(piece, board, false),
+            ROOK => generate_rook_moves(piece, board, false),
+            QUEEN => generate_queen_moves(piece, board, false),
+            _ => generate_king_moves(piece, board, false),
+        }.collect::<Vec<Move>>()
+    }).collect()
+}
+
+// Walks the tree checking the check and pin masks against making each move and looking for check.
+fn assert_legality_matches_make_move(board: &mut Board, depth: u8) {
+    let legality = LegalityInfo::new(board, board.to_move);
+
+    for mv in candidate_moves(board) {
+        assert_eq!(legality.is_legal(board, &mv), !would_move_cause_check(board, mv), "{:?} in {}", mv, board.to_fen());
+    }
+
+    if depth > 1 {
+        for mv in generate_all_moves_for_color(board, board.to_move).iter() {
+            make_move(board, *mv);
+            assert_legality_matches_make_move(board, depth - 1);
+            unmake_move(board, mv);
+        }
+    }
+}
+
+#[test]
+fn legality_matches_make_move() {
+    for fen in [START_FEN, KIWIPETE, EP_PINS, PROMOTIONS, DISCOVERED_CHECKS, MIDDLEGAME].iter() {
+        let mut board = Board::from_fen(fen);
+        assert_legality_matches_make_move(&mut board, 3);
+    }
+}
+
+#[test]
+fn captures_include_en_passant_and_quiet_promotions() {
+    let board = Board::from_fen("4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1");
+    let mut captures = generate_captures(&board, WHITE).iter().map(|mv| mv.to_pcn_string()).collect::<Vec<String>>();
+    captures.sort();
+
+    assert_eq!(captures, vec!["b7b8b", "b7b8n", "b7b8q", "b7b8r", "e5d6"]);
+}
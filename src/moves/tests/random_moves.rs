@@ -5,7 +5,7 @@ use super::generation::*;
 use super::make_move::*;
 use board::Board;
 
-const QUEEN_TEST_FEN: &'static str = "7k/8/8/1p3P2/8/8/5Q1p/K7 w - - 0 1";
+const QUEEN_TEST_FEN: &str = "7k/8/8/1p3P2/8/8/5Q1p/K7 w - - 0 1";
 
 pub fn test_move_making() {
     let mut board = Board::from_fen("1k6/8/1K6/8/8/8/8/R7 b - - 0 1"); 
@@ -0,0 +1,90 @@
+use board::Board;
+use constants::*;
+use moves::generation::generate_all_moves_for_color;
+use moves::san::SanError;
+use moves::types::Move;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+fn san_for(fen: &str, pcn: &str) -> String {
+    let board = Board::from_fen(fen);
+    Move::from_pcn_string(pcn, &board).to_san(&board)
+}
+
+#[test]
+fn to_san_basics() {
+    assert_eq!(san_for(START_FEN, "e2e4"), "e4");
+    assert_eq!(san_for(START_FEN, "g1f3"), "Nf3");
+    assert_eq!(san_for(KIWIPETE, "e1g1"), "O-O");
+    assert_eq!(san_for(KIWIPETE, "e1c1"), "O-O-O");
+    assert_eq!(san_for(KIWIPETE, "e2a6"), "Bxa6");
+    assert_eq!(san_for(KIWIPETE, "d5e6"), "dxe6");
+    assert_eq!(san_for("r1bqkbnr/p2ppp2/2n3p1/Pp6/2PNP2p/8/1P3PPP/RNBQKB1R w KQkq b6 7 4", "a5b6"), "axb6");
+}
+
+#[test]
+fn to_san_disambiguation() {
+    // knights on b1 and f3 can both reach d2, rooks on a1 and a5 both reach a3
+    let fen = "4k3/8/8/R7/8/5N2/8/RN2K3 w - - 0 1";
+    assert_eq!(san_for(fen, "b1d2"), "Nbd2");
+    assert_eq!(san_for(fen, "a1a3"), "R1a3");
+
+    // queens on c4, e4 and c2
+    let fen = "1k6/8/8/8/2Q1Q3/8/2Q5/4K3 w - - 0 1";
+    assert_eq!(san_for(fen, "c4d4"), "Qcd4");
+    assert_eq!(san_for(fen, "c2d3"), "Q2d3");
+
+    let fen = "k7/8/8/8/2Q5/8/2Q1Q3/K7 w - - 0 1";
+    assert_eq!(san_for(fen, "c2d3"), "Qc2d3");
+}
+
+#[test]
+fn to_san_promotions_and_checks() {
+    assert_eq!(san_for("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q"), "b8=Q+");
+    assert_eq!(san_for("2r1k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7c8n"), "bxc8=N");
+    assert_eq!(san_for("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"), "Ra8#");
+}
+
+#[test]
+fn from_san_round_trips_every_legal_move() {
+    let fens = [
+        START_FEN,
+        KIWIPETE,
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "1k6/8/8/8/2Q1Q3/8/2Q5/4K3 w - - 0 1",
+    ];
+
+    for fen in fens.iter() {
+        let board = Board::from_fen(fen);
+
+        for mv in generate_all_moves_for_color(&board, board.to_move) {
+            let san = mv.to_san(&board);
+            assert_eq!(Move::from_san(&san, &board), Ok(mv), "{} in {}", san, fen);
+        }
+    }
+}
+
+#[test]
+fn from_san_variations() {
+    let board = Board::from_fen(KIWIPETE);
+    let castle = Move::from_pcn_string("e1g1", &board);
+
+    assert_eq!(Move::from_san("0-0", &board), Ok(castle));
+    assert_eq!(Move::from_san("O-O!?", &board), Ok(castle));
+    assert_eq!(Move::from_san("Ba6", &board), Move::from_san("Bxa6", &board));
+
+    let board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+    assert_eq!(Move::from_san("b8Q", &board), Move::from_san("b8=Q+", &board));
+}
+
+#[test]
+fn from_san_errors() {
+    let board = Board::from_fen("4k3/8/8/R7/8/5N2/8/RN2K3 w - - 0 1");
+
+    assert_eq!(Move::from_san("Nd2", &board), Err(SanError::Ambiguous("Nd2".to_string())));
+    assert_eq!(Move::from_san("Ra3", &board), Err(SanError::Ambiguous("Ra3".to_string())));
+    assert_eq!(Move::from_san("Nc4", &board), Err(SanError::NoMatch("Nc4".to_string())));
+    assert_eq!(Move::from_san("O-O", &board), Err(SanError::NoMatch("O-O".to_string())));
+    assert_eq!(Move::from_san("Nz9", &board), Err(SanError::Malformed("Nz9".to_string())));
+    assert_eq!(Move::from_san("", &board), Err(SanError::Malformed("".to_string())));
+}
@@ -1,3 +1,5 @@
+#![allow(unused_parens, unused_variables)]
+#![allow(clippy::assign_op_pattern, clippy::map_clone, clippy::new_without_default, clippy::ptr_arg, clippy::redundant_field_names)]
 
 use types::*;
 use util::*;
@@ -17,6 +19,15 @@ pub struct Move {
 }
 
 impl Move {
+    // stands in for "no move", eg. in an empty transposition table slot
+    pub const NULL: Move = Move {
+        origin_piece: NO_PIECE,
+        dest_piece: NO_PIECE,
+        origin_pos: Position(0, 0),
+        dest_pos: Position(0, 0),
+        meta_info: NULL_MOVE
+    };
+
     pub fn new() -> Self {
         Move {
             origin_piece: NO_PIECE,
@@ -47,6 +58,15 @@ impl Move {
         is_piece && valid_capture && valid_ep_capture && valid_castling && valid_promotion && positions_valid
     }
 
+    // en passant and promotions that take a piece count as captures
+    pub fn is_capture(&self) -> bool {
+        self.meta_info & CAPTURE != 0 && self.meta_info != QUIET_MOVE
+    }
+
+    pub fn is_promotion(&self) -> bool {
+        self.meta_info >= KNIGHT_PROMOTION
+    }
+
     pub fn color(&self) -> u8 {
        if self.origin_piece >= B_PAWN && self.origin_piece <= B_KING { BLACK } else { WHITE }
     }
@@ -58,7 +78,7 @@ impl Move {
         };
 
         Move {
-            origin_piece: to_color(KING),
+            origin_piece: to_color(KING, color == WHITE),
             origin_pos: Position(KING_FILE, back_rank),
             dest_piece: NO_PIECE,
             dest_pos: Position(KING_SIDE_CASTLE_FILE, back_rank),
@@ -66,15 +86,15 @@ impl Move {
         }
     }
     
-    pub fn kingside_castle(color: Color) -> Move {
+    pub fn queenside_castle(color: Color) -> Move {
         let back_rank = match color {
             WHITE => WHITE_BACK_RANK,
             _ => BLACK_BACK_RANK,
         };
 
         Move {
-            origin_piece: to_color(QUEEN),
-            origin_pos: Position(QUEEN_FILE, back_rank),
+            origin_piece: to_color(KING, color == WHITE),
+            origin_pos: Position(KING_FILE, back_rank),
             dest_piece: NO_PIECE,
             dest_pos: Position(QUEEN_SIDE_CASTLE_FILE, back_rank),
             meta_info: QUEEN_CASTLE 
@@ -94,70 +114,71 @@ impl Move {
                 };
             }
             
-            "e1g1" if board.castling & W_OO => {
+            "e1g1" if board.castling & W_OO > 0 => {
                 return Move::kingside_castle(WHITE);
             },
 
-            "e8g8" if board.castling & B_OO => {
+            "e8g8" if board.castling & B_OO > 0 => {
                 return Move::kingside_castle(BLACK);
             },
 
-            "e1c1" if board.castling & W_OOO => {
+            "e1c1" if board.castling & W_OOO > 0 => {
                 return Move::queenside_castle(WHITE);
             },
-            "e8c8" if board.castling & B_OOO => {
+            "e8c8" if board.castling & B_OOO > 0 => {
                 return Move::queenside_castle(BLACK);
             },
 
             _ => ()
         };
 
-        let mv = Move::new();
+        let mut mv = Move::new();
         mv.meta_info = QUIET_MOVE;
          
-        let moves: Vec<char> = move_str.chars().collect();
+        let chars: Vec<char> = move_str.chars().collect();
+        assert!(chars.len() >= 4);
         
         mv.origin_pos = Position(
-            char_to_file(moves.nth(0)),
-            moves.nth(1).parse::<Rank>().unwrap()
+            char_to_file(&chars[0].to_string()),
+            (chars[1].to_digit(10).expect("invalid rank") as Rank) - 1
         );
         
         mv.dest_pos = Position(
-            char_to_file(moves.nth(2)),
-            moves.nth(3).parse::<Rank>().unwrap()
+            char_to_file(&chars[2].to_string()),
+            (chars[3].to_digit(10).expect("invalid rank") as Rank) - 1
         );
 
-        mv.origin_piece = board.getp(mv.origin_pos);
-        mv.dest_piece = board.getp(mv.dest_pos);
+        mv.origin_piece = board.mb.getp(mv.origin_pos);
+        mv.dest_piece = board.mb.getp(mv.dest_pos);
 
-        if moves.len() >= 5 {
-            assert_eq!(to_white(board.getp(mv.origin_pos)), W_PAWN);
+        if chars.len() >= 5 {
+            assert_eq!(to_white(mv.origin_piece), W_PAWN);
 
             // promotion
-            let promotion = moves.nth(4);
+            let promotion = chars[4];
 
             let is_promo_capture = mv.origin_pos.0 != mv.dest_pos.0;
 
             mv.meta_info = if is_promo_capture {
                 match promotion {
-                    "q" => QUEEN_PROMO_CAPTURE,
-                    "r" => ROOK_PROMO_CAPTURE,
-                    "b" => BISHOP_PROMO_CAPTURE,
-                    "n" => KNIGHT_PROMO_CAPTURE,
+                    'q' => QUEEN_PROMO_CAPTURE,
+                    'r' => ROOK_PROMO_CAPTURE,
+                    'b' => BISHOP_PROMO_CAPTURE,
+                    'n' => KNIGHT_PROMO_CAPTURE,
                     _ => 0
                 }
             } else {
                 match promotion {
-                    "q" => QUEEN_PROMOTION,
-                    "r" => ROOK_PROMOTION,
-                    "b" => BISHOP_PROMOTION,
-                    "n" => KNIGHT_PROMOTION,
+                    'q' => QUEEN_PROMOTION,
+                    'r' => ROOK_PROMOTION,
+                    'b' => BISHOP_PROMOTION,
+                    'n' => KNIGHT_PROMOTION,
                     _ => 0
                 }
             };
 
             return mv;
-        } else if to_white(board.getp(mv.origin_pos)) == W_PAWN {
+        } else if to_white(mv.origin_piece) == W_PAWN {
             let is_double = (mv.origin_pos.1 - mv.dest_pos.1).abs() > 1;
             if is_double { 
                 mv.meta_info = DOUBLE_PAWN_PUSH;
@@ -165,18 +186,43 @@ impl Move {
             }
             
             let is_capture = mv.origin_pos.0 != mv.dest_pos.0;
-            let is_ep_capture = is_capture && board.getp(mv.dest_pos) == NO_PIECE; 
+            let is_ep_capture = is_capture && mv.dest_piece == NO_PIECE; 
 
             if is_ep_capture {
-                let neighbor_is_pawn = to_white(board.get(mv.dest_pos.0, mv.origin_pos.1)) == W_PAWN;
-                assert!(!is_ep_capture || neighbor_is_pawn);
-                assert!(!is_ep_capture || board.en_passant == mv.dest_pos.0);
+                let neighbor_is_pawn = to_white(board.mb.get(mv.dest_pos.0, mv.origin_pos.1)) == W_PAWN;
+                assert!(neighbor_is_pawn);
+                assert!(board.en_passant == mv.dest_pos.0);
                 mv.meta_info = EP_CAPTURE;
+                return mv;
             }
         }
 
+        if mv.dest_piece != NO_PIECE {
+            mv.meta_info = CAPTURE;
+        }
+
         mv
     }
+
+    // to pure coordinate notation, eg. e2e4, e1g1 or a7a8q
+    pub fn to_pcn_string(&self) -> String {
+        if self.meta_info == NULL_MOVE {
+            return "0000".to_string();
+        }
+
+        let mut s = format!("{}{}{}{}",
+            file_to_char(self.origin_pos.0),
+            self.origin_pos.1 + 1,
+            file_to_char(self.dest_pos.0),
+            self.dest_pos.1 + 1,
+        );
+
+        if self.meta_info >= KNIGHT_PROMOTION {
+            s.push_str(&promotion_move_type_to_piece(self.meta_info, BLACK).to_lowercase());
+        }
+
+        s
+    }
 }
 
 fn promotion_move_type_to_piece(meta_info: MetaInfo, color: Color) -> &'static str {
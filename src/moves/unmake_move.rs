@@ -1,16 +1,23 @@
+#![allow(dead_code, unused_imports, unused_variables)]
+#![allow(clippy::assertions_on_constants, clippy::clone_on_copy, clippy::redundant_static_lifetimes)]
+
 use super::types::*;
 use util::{is_white, color_of, to_white, opposite_color, to_color};
 use constants::*;
 use board::Board;
 use types::{Position};
 
-use super::make_move::{make_move};
+use super::make_move::{make_move, make_null_move, squares_touched, sync_bitboards};
 use board::utils::{
     are_boards_equal, 
     assert_boards_equal
 };
 
 pub fn unmake_move(board: &mut Board, mv: &Move) {
+    if mv.meta_info == NULL_MOVE {
+        return unmake_null_move(board);
+    }
+
     assert!(mv.is_valid());
     assert!(board.to_move == opposite_color(color_of(mv.origin_piece)));
 
@@ -18,6 +25,12 @@ pub fn unmake_move(board: &mut Board, mv: &Move) {
 
     let last_move = board.history.pop().unwrap();
 
+    let (touched, touched_count) = squares_touched(mv);
+    let mut touched_pieces: [PieceType; 4] = [NO_PIECE; 4];
+    for i in 0..touched_count {
+        touched_pieces[i] = board.mb.getp(touched[i]);
+    }
+
     match mv.meta_info {
         QUIET_MOVE | DOUBLE_PAWN_PUSH => {
             assert!(board.mb.getp(mv.dest_pos) != NO_PIECE);
@@ -72,8 +85,26 @@ pub fn unmake_move(board: &mut Board, mv: &Move) {
         },
     }
 
+    sync_bitboards(board, &touched[..touched_count], &touched_pieces[..touched_count]);
+
+    // the hash from before the move is restored along with the rest of the context
+    board.reset_via_move_context(&last_move);
+
+    debug_assert_eq!(board.zhash, board.to_hash());
+    debug_assert_eq!(board.pawn_hash, board.to_pawn_hash());
+}
+
+// Undoes make_null_move, putting back the side to move along with the rest of the context.
+pub fn unmake_null_move(board: &mut Board) {
+    let last_move = board.history.pop().unwrap();
+    assert!(last_move.pending_move == Move::NULL);
+
+    // the context can't say whose turn it was, since a null move has no piece to go by
+    let to_move = opposite_color(board.to_move);
     board.reset_via_move_context(&last_move);
-    board.normalize();
+    board.to_move = to_move;
+
+    debug_assert_eq!(board.zhash, board.to_hash());
 }
 
 // Sort of randomized pieces
@@ -362,4 +393,26 @@ mod tests {
         unmake_move(&mut board, &mv);
         assert_boards_equal(&board, &orig_board);
     }
+
+    #[test]
+    fn null_move() {
+        let mut board = Board::from_fen("r2qk2r/p5bp/3p2p1/1p2Pp1n/2PB1Qb1/7P/PP4P1/RN2KB1R w KQkq f6 5 3");
+        let orig_board = board.clone();
+
+        make_null_move(&mut board);
+        assert_eq!(board.to_move, BLACK);
+        assert_eq!(board.en_passant, NO_EN_PASSANT);
+        assert_eq!(board.zhash, Board::from_fen("r2qk2r/p5bp/3p2p1/1p2Pp1n/2PB1Qb1/7P/PP4P1/RN2KB1R b KQkq - 6 3").zhash);
+
+        // and again, through the same path as "0000"
+        make_move(&mut board, Move::NULL);
+        assert_eq!(board.to_move, WHITE);
+        assert_eq!(board.fullmove_counter, orig_board.fullmove_counter + 1);
+
+        unmake_move(&mut board, &Move::NULL);
+        unmake_null_move(&mut board);
+        assert_boards_equal(&board, &orig_board);
+        assert_eq!(board.zhash, orig_board.zhash);
+        assert_eq!(board.pawn_hash, orig_board.pawn_hash);
+    }
 }
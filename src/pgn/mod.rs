@@ -0,0 +1,322 @@
+// Reading and writing games in Portable Game Notation.
+
+pub mod reader;
+pub mod writer;
+
+use std::error::Error;
+use std::fmt;
+
+use board::Board;
+use board::fen::FenError;
+use moves::make_move::make_move;
+use moves::san::SanError;
+use moves::types::Move;
+
+pub use self::reader::{read_game, read_games};
+pub use self::writer::write_game;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Unknown,
+}
+
+impl GameResult {
+    pub fn parse(s: &str) -> Option<GameResult> {
+        match s {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MoveNode {
+    pub mv: Move,
+    pub san: String,
+
+    // numeric annotation glyphs, with `!`, `?` and friends already turned into their numbers
+    pub nags: Vec<u8>,
+
+    // comments that follow the move
+    pub comments: Vec<String>,
+
+    // alternatives to this move, each starting from the position before it
+    pub variations: Vec<Variation>,
+}
+
+impl MoveNode {
+    pub fn new(mv: Move, san: String) -> Self {
+        MoveNode {
+            mv,
+            san,
+            nags: vec![],
+            comments: vec![],
+            variations: vec![],
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Variation {
+    // comments before the first move
+    pub comments: Vec<String>,
+    pub moves: Vec<MoveNode>,
+}
+
+impl Variation {
+    pub fn new() -> Self {
+        Variation {
+            comments: vec![],
+            moves: vec![],
+        }
+    }
+}
+
+impl Default for Variation {
+    fn default() -> Self {
+        Variation::new()
+    }
+}
+
+#[derive(Clone)]
+pub struct Game {
+    // in the order they were read
+    pub tags: Vec<(String, String)>,
+    pub starting_position: Board,
+    pub mainline: Variation,
+    pub result: GameResult,
+}
+
+impl Game {
+    // Builds a game from moves played out from `start`, recording the position in the `SetUp`
+    // and `FEN` tags when it isn't the standard one.
+    pub fn from_moves(start: &Board, moves: &[Move], result: GameResult) -> Self {
+        let mut tags = vec![];
+        if start.to_fen() != Board::starting_position().to_fen() {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), start.to_fen()));
+        }
+
+        let mut board = start.clone();
+        let mut mainline = Variation::new();
+        for mv in moves {
+            mainline.moves.push(MoveNode::new(*mv, mv.to_san(&board)));
+            make_move(&mut board, *mv);
+        }
+
+        Game {
+            tags,
+            starting_position: start.clone(),
+            mainline,
+            result,
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|tag| tag.0 == name).map(|tag| tag.1.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|tag| tag.0 == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    // the position at the end of the main line
+    pub fn final_position(&self) -> Board {
+        let mut board = self.starting_position.clone();
+        for node in &self.mainline.moves {
+            make_move(&mut board, node.mv);
+        }
+
+        board
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PgnErrorKind {
+    UnexpectedChar(char),
+    UnexpectedToken(String),
+    UnexpectedEof,
+    UnterminatedString,
+    UnterminatedComment,
+    BadNag(String),
+    BadFen(FenError),
+    BadMove(SanError),
+
+    // a variation has to follow the move it replaces
+    VariationWithoutMove,
+}
+
+impl fmt::Display for PgnErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PgnErrorKind::UnexpectedChar(c) => write!(f, "unexpected character '{}'", c),
+            PgnErrorKind::UnexpectedToken(ref s) => write!(f, "unexpected '{}'", s),
+            PgnErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            PgnErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            PgnErrorKind::UnterminatedComment => write!(f, "unterminated comment"),
+            PgnErrorKind::BadNag(ref s) => write!(f, "bad annotation glyph '{}'", s),
+            PgnErrorKind::BadFen(ref err) => write!(f, "bad FEN tag: {}", err),
+            PgnErrorKind::BadMove(ref err) => write!(f, "{}", err),
+            PgnErrorKind::VariationWithoutMove => write!(f, "variation before any move"),
+        }
+    }
+}
+
+// Lines and columns both start at 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: PgnErrorKind,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl Error for PgnError {}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(unused_imports)]
+    use constants::*;
+
+    #[allow(dead_code)]
+    const ANNOTATED: &str = r#"[Event "Casual \"blitz\""]
+[Site "?"]
+[Date "2024.01.02"]
+[Round "-"]
+[White "Morphy"]
+[Black "Duke"]
+[Result "1-0"]
+[Annotator "someone"]
+
+{Opera game} 1. e4 e5 2. Nf3 d6 3. d4 Bg4 $6 {weakening} 4. dxe5 Bxf3 5. Qxf3 dxe5
+6. Bc4 Nf6 7. Qb3 Qe7 8. Nc3 (8. Qxb7 Qb4+ 9. Qxb4 Bxb4+ (9... Nbd7) 10. c3) 8... c6
+9. Bg5 b5?! 10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6
+15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# ; mate
+1-0
+"#;
+
+    #[allow(dead_code)]
+    fn mainline_san(game: &Game) -> Vec<String> {
+        game.mainline.moves.iter().map(|node| node.san.clone()).collect()
+    }
+
+    #[test]
+    fn reads_annotated_game() {
+        let game = read_game(ANNOTATED).unwrap();
+
+        assert_eq!(game.tag("Event"), Some("Casual \"blitz\""));
+        assert_eq!(game.tag("Annotator"), Some("someone"));
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert_eq!(game.mainline.comments, vec!["Opera game".to_string()]);
+
+        let moves = &game.mainline.moves;
+        assert_eq!(moves.len(), 33);
+        assert_eq!(moves[5].san, "Bg4");
+        assert_eq!(moves[5].nags, vec![6]);
+        assert_eq!(moves[5].comments, vec!["weakening".to_string()]);
+        assert_eq!(moves[17].nags, vec![6]);
+        assert_eq!(moves[32].san, "Rd8#");
+        assert_eq!(moves[32].comments, vec!["mate".to_string()]);
+
+        // 8. Nc3 has one variation, which has its own variation on black's 9th move
+        let variation = &moves[14].variations[0];
+        assert_eq!(variation.moves.iter().map(|n| n.san.as_str()).collect::<Vec<&str>>(),
+                   vec!["Qxb7", "Qb4+", "Qxb4", "Bxb4+", "c3"]);
+        assert_eq!(variation.moves[3].variations[0].moves[0].san, "Nbd7");
+
+        let board = game.final_position();
+        assert!(::moves::is_color_in_check(&board, BLACK));
+    }
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let game = read_game(ANNOTATED).unwrap();
+        let written = write_game(&game);
+        let flat = written.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+        assert!(written.starts_with("[Event \"Casual \\\"blitz\\\"\"]\n[Site \"?\"]\n"));
+        assert!(written.contains("[Annotator \"someone\"]\n\n{Opera game} 1. e4 e5"));
+        assert!(flat.contains("3. d4 Bg4 $6 {weakening} 4. dxe5"));
+        assert!(flat.contains("8. Nc3 (8. Qxb7 Qb4+ 9. Qxb4 Bxb4+ (9... Nbd7) 10. c3) 8... c6"));
+        assert!(written.ends_with("17. Rd8# {mate} 1-0\n\n"));
+        assert!(written.lines().all(|line| line.len() < 80));
+
+        let reread = read_game(&written).unwrap();
+        assert_eq!(mainline_san(&reread), mainline_san(&game));
+        assert_eq!(reread.tags, game.tags);
+        assert_eq!(write_game(&reread), written);
+    }
+
+    #[test]
+    fn fen_setup_games() {
+        let pgn = "[SetUp \"1\"]\n[FEN \"4k3/1P6/8/8/8/8/8/4K3 w - - 0 40\"]\n\n40. b8=Q+ Kd7 41. Qb5+ *";
+        let game = read_game(pgn).unwrap();
+
+        assert_eq!(mainline_san(&game), vec!["b8=Q+", "Kd7", "Qb5+"]);
+        assert_eq!(game.result, GameResult::Unknown);
+
+        let start = game.starting_position.clone();
+        let moves = game.mainline.moves.iter().map(|n| n.mv).collect::<Vec<Move>>();
+        let rebuilt = Game::from_moves(&start, &moves, GameResult::Unknown);
+
+        assert_eq!(rebuilt.tag("FEN"), Some("4k3/1P6/8/8/8/8/8/4K3 w - - 0 40"));
+        assert!(write_game(&rebuilt).contains("\n40. b8=Q+ Kd7 41. Qb5+ *"));
+    }
+
+    #[test]
+    fn reads_several_games() {
+        let pgn = "[Event \"a\"]\n\n1. e4 1-0\n\n[Event \"b\"]\n\n1. d4 d5 1/2-1/2\n";
+        let games = read_games(pgn).unwrap();
+
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[1].tag("Event"), Some("b"));
+        assert_eq!(mainline_san(&games[1]), vec!["d4", "d5"]);
+        assert_eq!(games[1].result, GameResult::Draw);
+    }
+
+    #[test]
+    fn errors_carry_positions() {
+        let err = read_game("[Event \"x\"]\n\n1. e4 e5 2. Ke3 *").err().unwrap();
+        assert_eq!((err.line, err.column), (3, 13));
+        assert_eq!(err.kind, PgnErrorKind::BadMove(SanError::NoMatch("Ke3".to_string())));
+
+        let err = read_game("1. e4 {never closed\n2. d4 *").err().unwrap();
+        assert_eq!((err.line, err.column), (1, 7));
+        assert_eq!(err.kind, PgnErrorKind::UnterminatedComment);
+
+        let err = read_game("1. e4 (1. d4 *").err().unwrap();
+        assert_eq!(err.kind, PgnErrorKind::UnexpectedToken("*".to_string()));
+
+        let err = read_game("[FEN \"8/8 w - -\"]\n1. e4 *").err().unwrap();
+        assert_eq!((err.line, err.column), (1, 6));
+        assert_eq!(err.kind, PgnErrorKind::BadFen(FenError::WrongRankCount(2)));
+
+        let err = read_game("1. e4 e5").err().unwrap();
+        assert_eq!(err.kind, PgnErrorKind::UnexpectedEof);
+    }
+}
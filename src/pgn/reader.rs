@@ -0,0 +1,389 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use board::Board;
+use moves::make_move::make_move;
+use moves::types::Move;
+
+use super::{Game, GameResult, MoveNode, Variation, PgnError, PgnErrorKind};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Period,
+    Str(String),
+    Comment(String),
+    Nag(u8),
+
+    // tag names, SAN moves, move numbers and results
+    Symbol(String),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match *self {
+            Token::LBracket => "[".to_string(),
+            Token::RBracket => "]".to_string(),
+            Token::LParen => "(".to_string(),
+            Token::RParen => ")".to_string(),
+            Token::Period => ".".to_string(),
+            Token::Str(ref s) => format!("\"{}\"", s),
+            Token::Comment(ref s) => format!("{{{}}}", s),
+            Token::Nag(n) => format!("${}", n),
+            Token::Symbol(ref s) => s.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Spanned {
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Lexer {
+            chars: input.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+
+        if c == Some('\n') {
+            self.line += 1;
+            self.column = 1;
+        } else if c.is_some() {
+            self.column += 1;
+        }
+
+        c
+    }
+
+    fn error(&self, line: usize, column: usize, kind: PgnErrorKind) -> PgnError {
+        PgnError { line, column, kind }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Spanned>, PgnError> {
+        let mut tokens = vec![];
+
+        while let Some(&c) = self.chars.peek() {
+            let (line, column) = (self.line, self.column);
+
+            // a `%` in the first column escapes the rest of the line
+            if c == '%' && column == 1 {
+                while self.bump().is_some_and(|c| c != '\n') {}
+                continue;
+            }
+
+            if c.is_whitespace() {
+                self.bump();
+                continue;
+            }
+
+            let token = match c {
+                '[' => { self.bump(); Token::LBracket },
+                ']' => { self.bump(); Token::RBracket },
+                '(' => { self.bump(); Token::LParen },
+                ')' => { self.bump(); Token::RParen },
+                '.' => { self.bump(); Token::Period },
+                '*' => { self.bump(); Token::Symbol("*".to_string()) },
+                '"' => Token::Str(self.string(line, column)?),
+                '{' => Token::Comment(self.brace_comment(line, column)?),
+                ';' => Token::Comment(self.rest_of_line()),
+                '$' => Token::Nag(self.nag(line, column)?),
+                '!' | '?' => Token::Nag(self.suffix_annotation(line, column)?),
+                _ if c.is_alphanumeric() => Token::Symbol(self.symbol()),
+                _ => return Err(self.error(line, column, PgnErrorKind::UnexpectedChar(c))),
+            };
+
+            tokens.push(Spanned { token, line, column });
+        }
+
+        Ok(tokens)
+    }
+
+    fn string(&mut self, line: usize, column: usize) -> Result<String, PgnError> {
+        self.bump();
+        let mut s = String::new();
+
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some(c) => s.push(c),
+                    None => break,
+                },
+                Some('\n') | None => break,
+                Some(c) => s.push(c),
+            }
+        }
+
+        Err(self.error(line, column, PgnErrorKind::UnterminatedString))
+    }
+
+    fn brace_comment(&mut self, line: usize, column: usize) -> Result<String, PgnError> {
+        self.bump();
+        let mut s = String::new();
+
+        loop {
+            match self.bump() {
+                Some('}') => return Ok(s.trim().to_string()),
+                Some(c) => s.push(c),
+                None => return Err(self.error(line, column, PgnErrorKind::UnterminatedComment)),
+            }
+        }
+    }
+
+    fn rest_of_line(&mut self) -> String {
+        self.bump();
+        let mut s = String::new();
+
+        while let Some(c) = self.bump() {
+            if c == '\n' {
+                break;
+            }
+            s.push(c);
+        }
+
+        s.trim().to_string()
+    }
+
+    fn nag(&mut self, line: usize, column: usize) -> Result<u8, PgnError> {
+        self.bump();
+        let mut digits = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if !c.is_ascii_digit() {
+                break;
+            }
+            digits.push(c);
+            self.bump();
+        }
+
+        digits.parse::<u8>().map_err(|_| self.error(line, column, PgnErrorKind::BadNag(format!("${}", digits))))
+    }
+
+    // the traditional suffixes stand for the first six glyphs
+    fn suffix_annotation(&mut self, line: usize, column: usize) -> Result<u8, PgnError> {
+        let mut s = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if c != '!' && c != '?' {
+                break;
+            }
+            s.push(c);
+            self.bump();
+        }
+
+        match s.as_str() {
+            "!" => Ok(1),
+            "?" => Ok(2),
+            "!!" => Ok(3),
+            "??" => Ok(4),
+            "!?" => Ok(5),
+            "?!" => Ok(6),
+            _ => Err(self.error(line, column, PgnErrorKind::BadNag(s))),
+        }
+    }
+
+    fn symbol(&mut self) -> String {
+        let mut s = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_alphanumeric() || "_+#=:-/".contains(c)) {
+                break;
+            }
+            s.push(c);
+            self.bump();
+        }
+
+        s
+    }
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    pos: usize,
+
+    // where errors at the end of the input are reported
+    eof: (usize, usize),
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|s| &s.token)
+    }
+
+    fn next(&mut self) -> Result<Spanned, PgnError> {
+        match self.tokens.get(self.pos) {
+            Some(spanned) => {
+                self.pos += 1;
+                Ok(spanned.clone())
+            },
+            None => Err(PgnError { line: self.eof.0, column: self.eof.1, kind: PgnErrorKind::UnexpectedEof }),
+        }
+    }
+
+    fn unexpected(spanned: &Spanned) -> PgnError {
+        PgnError {
+            line: spanned.line,
+            column: spanned.column,
+            kind: PgnErrorKind::UnexpectedToken(spanned.token.describe()),
+        }
+    }
+
+    fn game(&mut self) -> Result<Game, PgnError> {
+        let mut tags = vec![];
+        let mut fen_tag = None;
+
+        while self.peek() == Some(&Token::LBracket) {
+            self.next()?;
+
+            let name_token = self.next()?;
+            let name = match name_token.token {
+                Token::Symbol(ref s) => s.clone(),
+                _ => return Err(Parser::unexpected(&name_token)),
+            };
+
+            let value_token = self.next()?;
+            let value = match value_token.token {
+                Token::Str(ref s) => s.clone(),
+                _ => return Err(Parser::unexpected(&value_token)),
+            };
+
+            if name == "FEN" {
+                fen_tag = Some((value.clone(), value_token.line, value_token.column));
+            }
+
+            let close = self.next()?;
+            if close.token != Token::RBracket {
+                return Err(Parser::unexpected(&close));
+            }
+
+            tags.push((name, value));
+        }
+
+        let starting_position = match fen_tag {
+            Some((fen, line, column)) => Board::try_from_fen(&fen)
+                .map_err(|err| PgnError { line, column, kind: PgnErrorKind::BadFen(err) })?,
+            None => Board::starting_position(),
+        };
+
+        let (mainline, result) = self.variation(starting_position.clone(), false)?;
+
+        Ok(Game {
+            tags,
+            starting_position,
+            mainline,
+            result: result.unwrap_or(GameResult::Unknown),
+        })
+    }
+
+    // Reads moves until the closing paren of a nested variation or the result that ends the main
+    // line.
+    fn variation(&mut self, mut board: Board, nested: bool) -> Result<(Variation, Option<GameResult>), PgnError> {
+        let mut variation = Variation::new();
+
+        // the position before the last move, which is where a variation on it starts
+        let mut before_last: Option<Board> = None;
+
+        loop {
+            let spanned = self.next()?;
+            let (line, column) = (spanned.line, spanned.column);
+
+            match spanned.token.clone() {
+                Token::Comment(comment) => match variation.moves.last_mut() {
+                    Some(node) => node.comments.push(comment),
+                    None => variation.comments.push(comment),
+                },
+
+                Token::Nag(nag) => match variation.moves.last_mut() {
+                    Some(node) => node.nags.push(nag),
+                    None => return Err(Parser::unexpected(&spanned)),
+                },
+
+                Token::Period => (),
+
+                // move numbers
+                Token::Symbol(ref s) if s.chars().all(|c| c.is_ascii_digit()) => (),
+
+                Token::Symbol(ref s) if GameResult::parse(s).is_some() => {
+                    if nested {
+                        return Err(Parser::unexpected(&spanned));
+                    }
+
+                    return Ok((variation, GameResult::parse(s)));
+                },
+
+                Token::Symbol(san) => {
+                    let mv: Move = Move::from_san(&san, &board)
+                        .map_err(|err| PgnError { line, column, kind: PgnErrorKind::BadMove(err) })?;
+
+                    let node = MoveNode::new(mv, mv.to_san(&board));
+                    before_last = Some(board.clone());
+                    make_move(&mut board, mv);
+                    variation.moves.push(node);
+                },
+
+                Token::LParen => {
+                    let start = match before_last {
+                        Some(ref b) => b.clone(),
+                        None => return Err(PgnError { line, column, kind: PgnErrorKind::VariationWithoutMove }),
+                    };
+
+                    let (sub, _) = self.variation(start, true)?;
+                    if let Some(node) = variation.moves.last_mut() {
+                        node.variations.push(sub);
+                    }
+                },
+
+                Token::RParen if nested => return Ok((variation, None)),
+
+                _ => return Err(Parser::unexpected(&spanned)),
+            }
+        }
+    }
+}
+
+// Reads every game in the input.
+pub fn read_games(input: &str) -> Result<Vec<Game>, PgnError> {
+    let lexer = Lexer::new(input);
+    let eof = input.lines().enumerate().last()
+        .map(|(i, line)| (i + 1, line.chars().count() + 1))
+        .unwrap_or((1, 1));
+
+    let mut parser = Parser {
+        tokens: lexer.tokenize()?,
+        pos: 0,
+        eof,
+    };
+
+    let mut games = vec![];
+    while parser.peek().is_some() {
+        games.push(parser.game()?);
+    }
+
+    Ok(games)
+}
+
+// Reads the first game in the input.
+pub fn read_game(input: &str) -> Result<Game, PgnError> {
+    match read_games(input)?.into_iter().next() {
+        Some(game) => Ok(game),
+        None => Err(PgnError { line: 1, column: 1, kind: PgnErrorKind::UnexpectedEof }),
+    }
+}
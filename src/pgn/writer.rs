@@ -0,0 +1,146 @@
+use constants::*;
+use util::opposite_color;
+
+use super::{Game, Variation};
+
+// export format keeps lines under 80 characters
+const MAX_LINE_LENGTH: usize = 79;
+
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+struct MovetextWriter {
+    out: String,
+    line_length: usize,
+
+    // set after an opening paren so that it sticks to the token that follows
+    open_paren: bool,
+}
+
+impl MovetextWriter {
+    fn push(&mut self, token: &str) {
+        let token = if self.open_paren { format!("({}", token) } else { token.to_string() };
+        self.open_paren = false;
+
+        if self.line_length > 0 && self.line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            self.out.push('\n');
+            self.line_length = 0;
+        }
+
+        if self.line_length > 0 {
+            self.out.push(' ');
+            self.line_length += 1;
+        }
+
+        self.out.push_str(&token);
+        self.line_length += token.len();
+    }
+
+    fn open(&mut self) {
+        self.open_paren = true;
+    }
+
+    fn close(&mut self) {
+        self.out.push(')');
+        self.line_length += 1;
+    }
+
+    // comments are written a word at a time so that long ones wrap too
+    fn comment(&mut self, comment: &str) {
+        let words = comment.split_whitespace().collect::<Vec<&str>>();
+
+        if words.is_empty() {
+            self.push("{}");
+            return;
+        }
+
+        for (i, word) in words.iter().enumerate() {
+            let mut token = word.to_string();
+            if i == 0 {
+                token.insert(0, '{');
+            }
+            if i == words.len() - 1 {
+                token.push('}');
+            }
+            self.push(&token);
+        }
+    }
+
+    fn variation(&mut self, variation: &Variation, mut number: u16, mut color: Color) {
+        for comment in &variation.comments {
+            self.comment(comment);
+        }
+
+        // black's moves only need a number when something interrupted the flow of moves
+        let mut needs_number = true;
+
+        for node in &variation.moves {
+            if color == WHITE {
+                self.push(&format!("{}.", number));
+            } else if needs_number {
+                self.push(&format!("{}...", number));
+            }
+
+            self.push(&node.san);
+            needs_number = false;
+
+            for nag in &node.nags {
+                self.push(&format!("${}", nag));
+            }
+
+            for comment in &node.comments {
+                self.comment(comment);
+                needs_number = true;
+            }
+
+            for sub in &node.variations {
+                self.open();
+                self.variation(sub, number, color);
+                self.close();
+                needs_number = true;
+            }
+
+            if color == BLACK {
+                number += 1;
+            }
+            color = opposite_color(color);
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn write_game(game: &Game) -> String {
+    let mut out = String::new();
+
+    for &(name, default) in SEVEN_TAG_ROSTER.iter() {
+        let value = if name == "Result" { game.result.as_str() } else { game.tag(name).unwrap_or(default) };
+        out.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+    }
+
+    for (name, value) in &game.tags {
+        if !SEVEN_TAG_ROSTER.iter().any(|&(n, _)| n == name) {
+            out.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+        }
+    }
+
+    out.push('\n');
+
+    let start = &game.starting_position;
+    let mut movetext = MovetextWriter { out, line_length: 0, open_paren: false };
+    movetext.variation(&game.mainline, start.fullmove_counter + 1, start.to_move);
+    movetext.push(game.result.as_str());
+
+    let mut out = movetext.out;
+    out.push_str("\n\n");
+    out
+}
@@ -1,10 +1,10 @@
 pub mod tests;
+pub mod quiescence;
 
 //use super::types::*;
-use regex::Regex;
 //use util::*;
 use constants::*;
-use util::{opposite_color, piece_type_to_str};
+use util::to_color;
 
 use board::Board;
 //use types::{};
@@ -12,29 +12,29 @@ use search::types::{
     Score,
     SearchHit,
     SearchParams,
-    SearchResults,
-    SearchStyle,
     MAX_SCORE,
     MIN_SCORE,
-    score_string,
-    color_string,
+    mate_in,
+    mated_in,
+    is_mate_score,
 };
 
 use search::observers::{
     SearchObservable,
-    SearchLogger,
     NoopObserver,
-    print_s,
 };
 
-use board::utils::{PieceIter};
-use moves::generation::{generate_all_moves_for_color, is_color_checkmated};
+use eval::Evaluator;
+use moves::generation::is_color_checkmated;
 use moves::types::{Move};
-use moves::make_move::{make_move};
-use moves::unmake_move::{unmake_move};
-use moves::{is_color_in_check, does_match_moves};
+use moves::picker::MovePicker;
+use moves::make_move::make_move;
+use moves::unmake_move::unmake_move;
+use moves::is_color_in_check;
+use engine::table::Bound;
+use self::quiescence::quiescence;
 
-pub fn is_terminal_state(board: &Board, search_params: &SearchParams, depth: u8, alpha: Score, beta: Score) -> bool {
+pub fn is_terminal_state(board: &Board, search_params: &SearchParams, depth: u8, _alpha: Score, _beta: Score) -> bool {
     // the utility function gets run in the next level, so intuitively we the cutoff refers to the
     // last depth that actually makes a move.
     
@@ -46,76 +46,125 @@ pub fn is_terminal_state(board: &Board, search_params: &SearchParams, depth: u8,
     depth >= search_params.search_ply_target - 1 || is_color_checkmated(board, board.to_move)
 }
 
-fn get_piece_value(piece: PieceType) -> Score {
-    match piece {
-        NO_PIECE => 0,
-        W_PAWN => 1,
-        W_KNIGHT => 3,
-        W_BISHOP => 3,
-        W_ROOK => 5,
-        W_QUEEN => 10,
-        W_KING => 100,
-        
-        B_PAWN => -1,
-        B_KNIGHT => -3,
-        B_BISHOP => -3,
-        B_ROOK => -5,
-        B_QUEEN => -10,
-        B_KING => -100,
-        _ => 0,
-    }
+
+// whether `color` has anything besides pawns and its king
+pub fn has_non_pawn_material(board: &Board, color: Color) -> bool {
+    [W_KNIGHT, W_BISHOP, W_ROOK, W_QUEEN].iter()
+        .any(|&piece| board.bb[to_color(piece, color == WHITE)] != 0)
 }
 
-// TODO:
-pub fn utility(board: &Board, depth: u8) -> Score {
-    let piece_iter = PieceIter::new(&board);
-    
-    let mut sum: Score = 0;
-    for piece in piece_iter {
-        //println!("{} -> {}", piece_type_to_str(piece), get_piece_value(piece));
-        sum = sum + get_piece_value(piece);
-    }
+// The static score from white's point of view, or the mate score if either side is mated.
+pub fn utility(board: &Board, evaluator: &dyn Evaluator, depth: u8) -> Score {
+    let sum = match board.to_move {
+        WHITE => evaluator.evaluate(board),
+        _ => -evaluator.evaluate(board),
+    };
 
     if is_color_checkmated(board, WHITE) {
-        return MIN_SCORE + depth as Score;       
+        mated_in(depth)
     } else if is_color_checkmated(board, BLACK) {
-        return MAX_SCORE - depth as Score;       
+        mate_in(depth)
     } else {
         sum
     }
 }
 
-fn best_score(color: Color) -> Score {
-    match color {
-        WHITE => MAX_SCORE,
-        _ => MIN_SCORE,
+// Stores a result in the table, if there is one.  Results from a search that was stopped part way
+// through can't be trusted, so they're dropped.
+fn record(board: &Board, search_params: &SearchParams, score: Score, mv: Move, draft: u8, ply: u8, bound: Bound) {
+    if let Some(ref table) = search_params.table {
+        if !search_params.is_stopped() {
+            table.lock().unwrap().record(board, score, mv, draft, ply, bound);
+        }
     }
 }
 
-fn worst_score(color: Color) -> Score {
-    match color {
-        WHITE => MIN_SCORE,
-        _ => MAX_SCORE,
+// Every move the search makes or takes back goes through these, so that the evaluator can follow
+// along.
+fn make_search_move(board: &mut Board, search_params: &SearchParams, mv: Move) {
+    make_move(board, mv);
+    search_params.evaluator.move_made(board, &mv);
+}
+
+fn unmake_search_move(board: &mut Board, search_params: &SearchParams, mv: &Move) {
+    unmake_move(board, mv);
+    search_params.evaluator.move_unmade(board, mv);
+}
+
+fn is_draw(board: &Board) -> bool {
+    board.is_repetition() || board.is_fifty_move_draw() || board.is_insufficient_material()
+}
+
+// Null moves are only tried with at least this many plies left, since below that the reduced
+// search would go straight to quiescence.
+const NULL_MOVE_MIN_DRAFT: u8 = 3;
+
+// Passing the turn is only a fair test of a position when the side to move isn't in check and
+// has pieces besides pawns.  In pawn endings zugzwang is common enough that passing would often
+// be the best move, if it were allowed.  Two null moves in a row prove nothing either.
+fn should_try_null_move(board: &Board, search_params: &SearchParams, draft: u8, their_best: Score, moves: &[Move]) -> bool {
+    let color = board.to_move;
+
+    // the evaluation is by far the most expensive test, so it goes last
+    draft >= NULL_MOVE_MIN_DRAFT
+        && moves.last() != Some(&Move::NULL)
+        && !is_mate_score(their_best)
+        && has_non_pawn_material(board, color)
+        && !is_color_in_check(board, color)
+        && search_params.evaluator.evaluate(board) >= their_best
+}
+
+// how many plies shallower the search after a null move goes, more when there's more to spare
+fn null_move_reduction(draft: u8) -> u8 {
+    if draft > 6 { 3 } else { 2 }
+}
+
+// Reductions only start after this many moves, which are searched at full depth however they
+// were ordered.
+const LMR_MIN_MOVES: usize = 3;
+
+// and only with at least this many plies left
+const LMR_MIN_DRAFT: u8 = 3;
+
+// Captures, promotions, checks and killers are the moves most likely to matter, and evading a
+// check has too few choices to be sure about any of them.
+fn can_reduce(mv: &Move, in_check: bool, gives_check: bool, killers: &[Move; 2]) -> bool {
+    !mv.is_capture() && !mv.is_promotion() && !in_check && !gives_check && !killers.contains(mv)
+}
+
+// Grows with both the plies left and how late the move comes, but always leaves at least one ply
+// before quiescence.
+fn late_move_reduction(draft: u8, move_number: usize) -> u8 {
+    if draft < LMR_MIN_DRAFT || move_number <= LMR_MIN_MOVES {
+        return 0;
     }
+
+    let reduction = 0.75 + (draft as f64).ln() * (move_number as f64).ln() / 2.25;
+    (reduction as u8).min(draft - 2)
+}
+
+fn frame_score(maybe_search_hit: &Option<SearchHit>, color: Color) -> Option<Score> {
+    maybe_search_hit.as_ref().map(|hit| hit.score_by_color(color))
 }
 
 fn search_hit(mv: &Move, score: Score, depth: u8, color_frame: Color, moves: Vec<Move>) -> SearchHit {
     SearchHit {
-        mv: mv.clone(),
+        mv: *mv,
         depth_searched: depth,
-        score: score,
+        score,
         color: color_frame,
         move_list: moves,
     } 
 }
 
 pub fn ab_search(board: &mut Board, search_params: &SearchParams) -> Option<SearchHit> {
-    board.print_board();
     let mut moves: Vec<Move> = vec![]; 
-    let our_best = worst_score(board.to_move);
-    let their_best = worst_score(opposite_color(board.to_move));
-    let observer: Box<SearchObservable> = Box::new(NoopObserver{});
-    return _ab_search(board, search_params, 0, our_best, their_best, &mut moves, &observer);
+    _ab_search(board, search_params, 0, MIN_SCORE, MAX_SCORE, &mut moves, &NoopObserver{})
+}
+
+pub fn ab_search_observed(board: &mut Board, search_params: &SearchParams, observer: &dyn SearchObservable) -> Option<SearchHit> {
+    let mut moves: Vec<Move> = vec![]; 
+    _ab_search(board, search_params, 0, MIN_SCORE, MAX_SCORE, &mut moves, observer)
 }
 
 // Responsibility: The board should be back in the original state after the function has 
@@ -125,7 +174,7 @@ pub fn ab_search(board: &mut Board, search_params: &SearchParams) -> Option<Sear
 // Because we are using negamax, in each frame `our_best` is framed positively and `their_best` is
 // framed negatively.  That is, if we want to compare a score in the current frame to `their_best`
 // then you need to negate one of them.
-pub fn _ab_search(board: &mut Board, search_params: &SearchParams, depth: u8, _our_best: Score, their_best: Score, moves: &mut Vec<Move>, observer: &Box<SearchObservable>) -> Option<SearchHit> {
+pub fn _ab_search(board: &mut Board, search_params: &SearchParams, depth: u8, _our_best: Score, their_best: Score, moves: &mut Vec<Move>, observer: &dyn SearchObservable) -> Option<SearchHit> {
     let frame_color = board.to_move;
 
     if depth == 0 {
@@ -134,20 +183,33 @@ pub fn _ab_search(board: &mut Board, search_params: &SearchParams, depth: u8, _o
         observer.recursed(frame_color, depth, _our_best, their_best, moves);
     }
 
-    let mut max: Score = best_score(frame_color);
+    // once stopped, every node below the root bails out so the search unwinds quickly
+    if depth > 0 && search_params.is_stopped() {
+        return None;
+    }
+
+    // drawn by the rules, whatever the material says.  The root is left to search since it has to
+    // come up with a move anyway.
+    if depth > 0 && is_draw(board) {
+        observer.leaf_node(0, frame_color, depth, _our_best, their_best, moves);
+        return Some(search_hit(moves.last().unwrap(), 0, depth, frame_color, moves.clone()));
+    }
+
     let mut best_search_hit: Option<SearchHit> = None;
     let mut our_best = _our_best;
 
     let mut our_local_best: Score = MIN_SCORE;
 
-    // if at leaf node, calculate utility score
+    // if at leaf node, settle any captures that are still hanging and score what's left
     // check for a terminal condition
     if is_terminal_state(board, search_params, depth, our_best, their_best) {
-        let score = utility(board, depth);
+        let frame_score = quiescence(board, search_params, depth, our_best, their_best, moves, observer);
 
-        let mv: Move = moves.last().unwrap().clone();
+        let mv: Move = *moves.last().unwrap();
         
-        let search_hit = search_hit(&mv, score, depth, frame_color, moves.clone());
+        let mut search_hit = search_hit(&mv, 0, depth, frame_color, moves.clone());
+        search_hit.set_score_by_color(frame_score, frame_color);
+        let score = search_hit.score;
 
 
         observer.leaf_node(score, frame_color, depth, our_best, their_best, moves);
@@ -155,32 +217,109 @@ pub fn _ab_search(board: &mut Board, search_params: &SearchParams, depth: u8, _o
         return Some(search_hit);
     }
     
-    // enumerate all possible moves
-    // for each move, recurse and find the best move.  
-    //
-    // todo: check refutation table to apply the refutation heuristic
-    let all_moves = generate_all_moves_for_color(board, frame_color);
+    // plies left to search below this node, which is what table entries are compared on
+    let draft = search_params.search_ply_target - 1 - depth;
 
-    observer.moves_generated(depth, &all_moves, moves);
-    
-    if all_moves.len() == 0 {
-        print_s(&"stalemate".to_string(), depth, moves);
+    let mut hash_move = Move::NULL;
+    if let Some(ref table) = search_params.table {
+        let (table_score, table_move) = table.lock().unwrap().probe(board.zhash, draft, depth, our_best, their_best);
 
-        //board.print_board_indent(depth as usize);
-        // No legal moves means a stalemate, which has the value 0
-        return Some(search_hit(&moves.first().unwrap().clone(), 0, depth, frame_color, moves.clone()));
+        // the root always searches so that there's a move list to report
+        if let (Some(score), true) = (table_score, depth > 0) {
+            let mut search_hit = search_hit(moves.last().unwrap(), 0, depth, frame_color, moves.clone());
+            search_hit.set_score_by_color(score, frame_color);
+            return Some(search_hit);
+        }
+
+        hash_move = table_move;
     }
-    
-    for mv in all_moves.iter() {
+
+    // null-move pruning: if we're still doing well enough for a cutoff after letting the other side
+    // move twice in a row, then one of our actual moves almost certainly does too.
+    if depth > 0 && should_try_null_move(board, search_params, draft, their_best, moves) {
+        let reduced_params = search_params.reduced(null_move_reduction(draft));
+
+        moves.push(Move::NULL);
+        make_search_move(board, search_params, Move::NULL);
+
+        // a zero width window, since all we want to know is whether the score gets to their best
+        let maybe_search_hit = _ab_search(board, &reduced_params, depth+1, -their_best, (-their_best).saturating_add(1), moves, observer);
+
+        unmake_search_move(board, search_params, &Move::NULL);
+        moves.pop();
+
+        let null_score = maybe_search_hit.map(|hit| hit.score_by_color(frame_color));
+        if null_score.is_some_and(|score| score >= their_best) && !search_params.is_stopped() {
+            observer.null_move_cutoff(depth, their_best);
+
+            // the null move's score isn't to be trusted beyond proving the cutoff, mates especially
+            let mut search_hit = search_hit(moves.last().unwrap(), 0, depth, frame_color, moves.clone());
+            search_hit.set_score_by_color(their_best, frame_color);
+            return Some(search_hit);
+        }
+    }
+
+    // for each move, recurse and find the best move.  The picker hands them out best guess first,
+    // starting with the best move from an earlier search of this position, then the killers for
+    // this ply.
+    let in_check = is_color_in_check(board, frame_color);
+    let killers = search_params.heuristics.borrow().killers(depth);
+    let mut picker = MovePicker::new(hash_move, killers);
+    let mut moves_searched = 0;
+    let mut best_move = Move::NULL;
+
+    // the quiet moves that didn't cut off, which lose history if a later one does
+    let mut quiets_tried: Vec<Move> = vec![];
+
+    loop {
+        // the heuristics can't stay borrowed while the children search and update them
+        let next = picker.next(board, &search_params.heuristics.borrow());
+        let mv = match next {
+            Some(ref mv) => mv,
+            None => break,
+        };
+        moves_searched += 1;
         //print_s(&format!("[{}] Making move {:?}", depth, mv), depth, moves);
 
-        moves.push(mv.clone());
-        make_move(board, mv.clone());
+        moves.push(*mv);
+        make_search_move(board, search_params, *mv);
+
+        // principal variation search: the first move gets the full window, and the rest only have
+        // to show they can't beat it, which a zero width window answers faster.  Late quiet moves
+        // are searched shallower too, and anything that looks like it might beat the first move
+        // after all is searched again properly.
+        let mut maybe_search_hit: Option<SearchHit>;
+        if moves_searched == 1 {
+            maybe_search_hit = _ab_search(board, search_params, depth+1, -their_best, -our_best, moves, observer);
+        } else {
+            let gives_check = is_color_in_check(board, board.to_move);
+            let reduction = if can_reduce(mv, in_check, gives_check, &killers) {
+                late_move_reduction(draft, moves_searched)
+            } else {
+                0
+            };
+
+            let reduced_params;
+            let params = if reduction > 0 {
+                reduced_params = search_params.reduced(reduction);
+                &reduced_params
+            } else {
+                search_params
+            };
+
+            let null_window = (-our_best - 1, -our_best);
+            maybe_search_hit = _ab_search(board, params, depth+1, null_window.0, null_window.1, moves, observer);
+
+            if reduction > 0 && frame_score(&maybe_search_hit, frame_color).is_some_and(|score| score > our_best) {
+                maybe_search_hit = _ab_search(board, search_params, depth+1, null_window.0, null_window.1, moves, observer);
+            }
 
-        let mut maybe_search_hit: Option<SearchHit> = _ab_search(board, search_params, depth+1, -their_best, -our_best, moves, observer);
+            if frame_score(&maybe_search_hit, frame_color).is_some_and(|score| score > our_best && score < their_best) {
+                maybe_search_hit = _ab_search(board, search_params, depth+1, -their_best, -our_best, moves, observer);
+            }
+        }
         
-        if maybe_search_hit.is_some() {
-            let mut search_hit = maybe_search_hit.unwrap();
+        if let Some(search_hit) = maybe_search_hit {
             let score = search_hit.score_by_color(frame_color);
 
             //print_s(&format!("[{}] Move {:?} has score {} {:?}", depth, mv, score, search_hit), depth, moves);
@@ -200,20 +339,10 @@ pub fn _ab_search(board: &mut Board, search_params: &SearchParams, depth: u8, _o
             //     print_s(&format!("[{}] Found what appears to be a checkmate.  With score: {}", depth, score), depth, moves);
             // }
             
-            if score + depth as Score == best_score(frame_color) {
-                // if it's a checkmate then we can just immediately return because that's the
-                // highest score possible.
-                // TODO: Technically this implementation loses us the ability to prioritize
-                // checkmates at smaller depths.
-                
-                //board.print_board(); 
-                //print_s(&format!("[{}] Returning early because we found a checkmate", depth), depth, moves);
-
-                unmake_move(board, mv);
-                moves.pop();
-                
-                return Some(search_hit)
-            } else if score >= their_best { 
+            // Mates aren't returned early, a mate further down the list can still be a faster
+            // one.  They score higher the fewer plies they are from the root, so the search
+            // already prefers the fastest mate, and the slowest way of getting mated.
+            if score >= their_best { 
                 // fail high (ie. prune)
                 // 
                 // If we find a score that is better than a score that the opposite side could
@@ -221,14 +350,21 @@ pub fn _ab_search(board: &mut Board, search_params: &SearchParams, depth: u8, _o
                 // 
                 
                 observer.fail_high(score, frame_color, depth, our_best, their_best, moves);
-                unmake_move(board, mv);
+                observer.cutoff(depth, mv, moves_searched);
+                unmake_search_move(board, search_params, mv);
                 moves.pop();
+
+                record(board, search_params, score, *mv, draft, depth, Bound::Lower);
+                if !search_params.is_stopped() {
+                    search_params.heuristics.borrow_mut().record_cutoff(mv, depth, draft, &quiets_tried);
+                }
                 
                 // Even if we are pruning we must return a score.  If we return none, then the maximizer 2 plys before us will report that this line is unfruitful, which 
                 return Some(search_hit);
             } else if score > our_local_best {
                 our_local_best = score;
                 best_search_hit = Some(search_hit);
+                best_move = *mv;
                 observer.new_best(depth, our_local_best, moves);
 
                 if our_local_best > our_best {
@@ -237,15 +373,31 @@ pub fn _ab_search(board: &mut Board, search_params: &SearchParams, depth: u8, _o
                 }
             }
             
-            unmake_move(board, mv);
+            unmake_search_move(board, search_params, mv);
             moves.pop();
+
+            if !mv.is_capture() && !mv.is_promotion() {
+                quiets_tried.push(*mv);
+            }
         } else {
-            print_s(&format!("[{}] no search hits for move {:?}", depth, mv), depth, moves);
-            unmake_move(board, mv);
+            unmake_search_move(board, search_params, mv);
             moves.pop();
         }
     }
         
+    if moves_searched == 0 {
+        //board.print_board_indent(depth as usize);
+        // No legal moves means a stalemate, which has the value 0
+        return Some(search_hit(&moves.first().unwrap().clone(), 0, depth, frame_color, moves.clone()));
+    }
+
+    observer.all_moves_searched(depth, moves_searched);
+
+    if best_search_hit.is_some() {
+        let bound = if our_local_best > _our_best { Bound::Exact } else { Bound::Upper };
+        record(board, search_params, our_local_best, best_move, draft, depth, bound);
+    }
+
     if depth == 0 {
         observer.finished(&best_search_hit);
     }
@@ -0,0 +1,115 @@
+use constants::*;
+use util::to_white;
+
+use board::Board;
+use search::types::{Score, SearchParams, MIN_SCORE, mated_in};
+use search::observers::SearchObservable;
+
+use moves::generation::{generate_all_moves_for_color, generate_captures};
+use moves::types::Move;
+use moves::is_color_in_check;
+use moves::see::see_ge;
+
+use eval::get_piece_value;
+use super::{make_search_move, unmake_search_move};
+
+// A capture is skipped when even winning the piece outright, plus this much, can't bring the
+// score up to alpha.
+const DELTA_MARGIN: Score = 200;
+
+// the most a move can change the material balance by
+fn material_gain(mv: &Move) -> Score {
+    let captured = match mv.meta_info {
+        EP_CAPTURE => W_PAWN,
+        _ if mv.is_capture() => to_white(mv.dest_piece),
+        _ => NO_PIECE,
+    };
+
+    let promoted = if mv.is_promotion() {
+        let piece = [W_KNIGHT, W_BISHOP, W_ROOK, W_QUEEN][(mv.meta_info & 0b11) as usize];
+        get_piece_value(piece) - get_piece_value(W_PAWN)
+    } else {
+        0
+    };
+
+    get_piece_value(captured) + promoted
+}
+
+// Keeps searching captures and promotions past the depth limit until the position is quiet, so
+// that leaf scores aren't taken in the middle of an exchange.  The side to move can always
+// "stand pat" on the static score instead of capturing, except when in check, where every
+// evasion is searched and having none is mate.
+//
+// Like `our_best` and `their_best`, the returned score is framed for the side to move.
+pub fn quiescence(board: &mut Board, search_params: &SearchParams, depth: u8, alpha: Score, beta: Score, moves: &mut Vec<Move>, observer: &dyn SearchObservable) -> Score {
+    let frame_color = board.to_move;
+    let in_check = is_color_in_check(board, frame_color);
+
+    let all_moves = if in_check {
+        generate_all_moves_for_color(board, frame_color)
+    } else {
+        generate_captures(board, frame_color)
+    };
+
+    if in_check && all_moves.is_empty() {
+        return mated_in(depth);
+    }
+
+    let stand_pat = search_params.evaluator.evaluate(board);
+
+    // the result is going to be thrown away, so don't spend any longer on it
+    if search_params.is_stopped() {
+        return stand_pat;
+    }
+
+    let mut alpha = alpha;
+    let mut best = MIN_SCORE;
+
+    if !in_check {
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+
+        best = stand_pat;
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+    }
+
+    for mv in all_moves.iter() {
+        let optimistic = stand_pat + material_gain(mv) + DELTA_MARGIN;
+        if !in_check && optimistic < alpha {
+            // the move could still have been worth this much, so a fail low can't claim any less
+            best = best.max(optimistic);
+            continue;
+        }
+
+        // standing pat is always at least as good as an exchange that loses material
+        if !in_check && !see_ge(board, mv, 0) {
+            continue;
+        }
+
+        moves.push(*mv);
+        make_search_move(board, search_params, *mv);
+        observer.recursed(board.to_move, depth + 1, -beta, -alpha, moves);
+
+        let score = -quiescence(board, search_params, depth + 1, -beta, -alpha, moves, observer);
+
+        unmake_search_move(board, search_params, mv);
+        moves.pop();
+
+        if score >= beta {
+            return score;
+        }
+
+        if score > best {
+            best = score;
+        }
+
+        if score > alpha {
+            alpha = score;
+        }
+    }
+
+    best
+}
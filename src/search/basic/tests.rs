@@ -1,3 +1,6 @@
+// Only the test harness calls into this module, so outside `cargo test` all of it is unused.
+#![allow(dead_code, unused_imports)]
+
 //use super::types::*;
 // use regex::Regex;
 // //use util::*;
@@ -35,60 +38,52 @@
 // After 1 ply the score -3.
 // After 3 ply white is ahead
 // but after 3 black won.
-const SEARCH_TEST1: &'static str = "6bk/5p2/P4Pp1/5pP1/4pP1p/3pPp2/P2PpP2/4K3 w - - 0 1";
-const SEARCH_TEST2: &'static str = "6bk/5p2/P4Pp1/5pP1/4pP1p/3pPp2/3PpP2/4K3 w - - 0 1";
+const SEARCH_TEST1: &str = "6bk/5p2/P4Pp1/5pP1/4pP1p/3pPp2/P2PpP2/4K3 w - - 0 1";
+const SEARCH_TEST2: &str = "6bk/5p2/P4Pp1/5pP1/4pP1p/3pPp2/3PpP2/4K3 w - - 0 1";
 
 #[allow(unused_imports)]
 use super::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use engine::table::Table;
+use search::observers::NodeCounter;
+use eval::{Evaluator, MaterialEvaluator, PstEvaluator, evaluate};
 
 #[test]        
 fn search_depth_2() {
-    let search_params = SearchParams {
-        search_ply_target: 2,
-        orig_to_move: WHITE,
-        search_style: SearchStyle::BASIC
-    };
+    let search_params = SearchParams::new(2, WHITE);
 
     let mut board = Board::from_fen(SEARCH_TEST2);
     let search_hit = ab_search(&mut board, &search_params).unwrap();
-    assert_eq!(search_hit.score, -4);
+    assert_eq!(search_hit.score, -459);
 }
 
 #[test]        
 fn search_depth_4() {
-    let search_params = SearchParams {
-        search_ply_target: 4,
-        orig_to_move: WHITE,
-        search_style: SearchStyle::BASIC
-    };
+    let search_params = SearchParams::new(4, WHITE);
 
     let mut board = Board::from_fen(SEARCH_TEST2);
     let search_hit = ab_search(&mut board, &search_params).unwrap();
     println!("search_hit: {:?}", search_hit);
-    assert_eq!(search_hit.score_by_color(WHITE), 5);
+    assert_eq!(search_hit.score_by_color(WHITE), -2);
 }
 
 #[test]        
 fn search_depth_6() {
-    let search_params = SearchParams {
-        search_ply_target: 6,
-        orig_to_move: WHITE,
-        search_style: SearchStyle::BASIC
-    };
+    let search_params = SearchParams::new(6, WHITE);
 
     let mut board = Board::from_fen(SEARCH_TEST2);
     let search_hit = ab_search(&mut board, &search_params).unwrap();
     println!("search_hit: {:?}", search_hit);
-    assert_eq!(search_hit.score_by_color(WHITE), 8);
+    // with captures settled at the horizon, black's pawns come out ahead, the h-pawn a step from
+    // queening with nothing in its way
+    assert_eq!(search_hit.score_by_color(WHITE), -910);
 }
 
 #[test]        
 fn search_depth_8() {
-    let search_params = SearchParams {
-        search_ply_target: 8,
-        orig_to_move: WHITE,
-        search_style: SearchStyle::BASIC
-    };
+    let search_params = SearchParams::new(8, WHITE);
 
     let mut board = Board::from_fen(SEARCH_TEST2);
     let search_hit = ab_search(&mut board, &search_params).unwrap();
@@ -100,11 +95,7 @@ fn search_depth_8() {
 
 #[test]        
 fn search_depth_possible_mate() {
-    let search_params = SearchParams {
-        search_ply_target: 9,
-        orig_to_move: WHITE,
-        search_style: SearchStyle::BASIC
-    };
+    let search_params = SearchParams::new(9, WHITE);
 
     let mut board = Board::from_fen(SEARCH_TEST1);
     let search_hit = ab_search(&mut board, &search_params).unwrap();
@@ -115,6 +106,321 @@ fn search_depth_possible_mate() {
 
 #[test]        
 fn test_utility() {
-    let mut board = Board::from_fen(SEARCH_TEST1);
-    assert_eq!(utility(&board, 0), -3);
+    let board = Board::from_fen(SEARCH_TEST1);
+    assert_eq!(utility(&board, &PstEvaluator {}, 0), -376);
+    assert_eq!(utility(&board, &MaterialEvaluator {}, 0), -300);
+}
+#[test]
+fn search_black_to_move() {
+    // the white knight is hanging
+    let mut board = Board::from_fen("4k3/8/8/3q4/2N5/8/7K/8 b - - 0 1");
+    let search_hit = ab_search(&mut board, &SearchParams::new(3, BLACK)).unwrap();
+
+    assert_eq!(search_hit.move_list[0].to_pcn_string(), "d5c4");
+    assert_eq!(search_hit.score_by_color(BLACK), 964);
+}
+
+fn search_with_table(fen: &str, ply_target: u8, table: &Arc<Mutex<Table>>) -> (Score, usize) {
+    let search_params = SearchParams {
+        table: Some(table.clone()),
+        ..SearchParams::new(ply_target, WHITE)
+    };
+
+    let nodes = Rc::new(Cell::new(0));
+    let observer = NodeCounter { nodes: nodes.clone() };
+
+    let mut board = Board::from_fen(fen);
+    let search_hit = ab_search_observed(&mut board, &search_params, &observer).unwrap();
+    (search_hit.score_by_color(WHITE), nodes.get())
+}
+
+#[test]
+fn table_keeps_scores_and_saves_nodes() {
+    let table = Arc::new(Mutex::new(Table::empty_mb(1)));
+
+    for &(ply_target, score) in [(4, -2), (6, -910)].iter() {
+        let (found, first_nodes) = search_with_table(SEARCH_TEST2, ply_target, &table);
+        assert_eq!(found, score);
+
+        // a second search of the same position is mostly answered by the table
+        let (found, second_nodes) = search_with_table(SEARCH_TEST2, ply_target, &table);
+        assert_eq!(found, score);
+        assert!(second_nodes < first_nodes, "{} vs {}", second_nodes, first_nodes);
+    }
+}
+fn quiesce(fen: &str) -> Score {
+    let mut board = Board::from_fen(fen);
+    let observer = NoopObserver{};
+    let search_params = SearchParams::new(1, board.to_move);
+    quiescence::quiescence(&mut board, &search_params, 0, MIN_SCORE, MAX_SCORE, &mut vec![], &observer)
+}
+
+#[allow(dead_code)]
+fn static_score(fen: &str) -> Score {
+    evaluate(&Board::from_fen(fen))
+}
+
+#[test]
+fn quiescence_stands_pat_or_captures() {
+    // nothing to take, so the static evaluation
+    let fen = "4k3/8/2p5/8/8/8/3Q4/4K3 w - - 0 1";
+    assert_eq!(quiesce(fen), static_score(fen));
+
+    // the d5 pawn is defended, taking it loses the queen
+    let fen = "4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1";
+    assert_eq!(quiesce(fen), static_score(fen));
+
+    // a loose rook is won, while black can only trade for the defended queen
+    assert_eq!(quiesce("4k3/8/8/3r4/8/8/3Q4/4K3 w - - 0 1"), static_score("4k3/8/8/3Q4/8/8/8/4K3 b - - 0 1"));
+    assert_eq!(quiesce("4k3/8/8/3r4/8/8/3Q4/4K3 b - - 0 1"), -static_score("4k3/8/8/8/8/8/3K4/8 b - - 0 1"));
+}
+
+#[test]
+fn quiescence_searches_check_evasions() {
+    // mated, even though there's nothing to capture
+    assert_eq!(quiesce("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1"), MIN_SCORE);
+
+    // standing pat would be a queen for a knight up, but the king has to step out of the fork
+    // and the queen goes, leaving black a pawn to win with
+    let fen = "7k/7p/8/8/8/Q7/2n5/4K3 w - - 0 1";
+    assert!(static_score(fen) > 500);
+    assert!(quiesce(fen) < -100, "{}", quiesce(fen));
+}
+
+#[test]
+fn search_horizon_sees_recapture() {
+    let mut board = Board::from_fen("4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1");
+    let search_hit = ab_search(&mut board, &SearchParams::new(2, WHITE)).unwrap();
+
+    assert!(search_hit.move_list[0].to_pcn_string() != "d2d5");
+    assert_eq!(search_hit.score_by_color(WHITE), 770);
+}
+
+#[test]
+fn cutoff_stats_are_observed() {
+    use search::observers::{CutoffCounter, CutoffStats};
+    use search::heuristics::Heuristics;
+    use std::cell::RefCell;
+
+    let heuristics = Rc::new(RefCell::new(Heuristics::new()));
+    let mut first_move_rates = vec![];
+
+    // the second search starts with the killers and history the first one learned
+    for _ in 0..2 {
+        let search_params = SearchParams {
+            heuristics: heuristics.clone(),
+            ..SearchParams::new(6, WHITE)
+        };
+
+        let stats = Rc::new(CutoffStats::new());
+        let observer = CutoffCounter { stats: stats.clone() };
+
+        let mut board = Board::from_fen(SEARCH_TEST2);
+        let search_hit = ab_search_observed(&mut board, &search_params, &observer).unwrap();
+        assert_eq!(search_hit.score_by_color(WHITE), -910);
+
+        assert!(stats.cutoffs.get() > 0);
+        assert!(stats.cutoff_rate() > 0.0 && stats.cutoff_rate() <= 1.0);
+        assert!(stats.average_cutoff_move() >= 1.0);
+        first_move_rates.push(stats.first_move_rate());
+    }
+
+    assert!(first_move_rates[1] >= first_move_rates[0], "{:?}", first_move_rates);
+}
+
+#[allow(dead_code)]
+fn null_move_cutoffs(fen: &str, ply_target: u8) -> usize {
+    use search::observers::{CutoffCounter, CutoffStats};
+
+    let stats = Rc::new(CutoffStats::new());
+    let observer = CutoffCounter { stats: stats.clone() };
+
+    // material only, so that what gets pruned doesn't move with the evaluation's tuning
+    let mut board = Board::from_fen(fen);
+    let search_params = SearchParams {
+        evaluator: Rc::new(MaterialEvaluator {}),
+        ..SearchParams::new(ply_target, board.to_move)
+    };
+    ab_search_observed(&mut board, &search_params, &observer).unwrap();
+    stats.null_move_cutoffs.get()
+}
+
+#[test]
+fn null_moves_prune_when_safe() {
+    // white is a rook up, so passing is often still enough
+    assert!(null_move_cutoffs("4k3/pp3ppp/8/8/8/8/PP3PPP/R3K3 w - - 0 1", 6) > 0);
+
+    // a pawn ending, where passing would dodge zugzwang
+    assert_eq!(null_move_cutoffs("8/4k3/8/3pP3/3P4/4K3/8/8 w - - 0 1", 6), 0);
+}
+
+#[test]
+fn null_move_search_keeps_the_result() {
+    // the hanging knight still gets taken with null moves cutting off around it
+    let mut board = Board::from_fen("4k3/8/8/3q4/2N5/8/7K/8 b - - 0 1");
+    let search_hit = ab_search(&mut board, &SearchParams::new(6, BLACK)).unwrap();
+    assert_eq!(search_hit.move_list[0].to_pcn_string(), "d5c4");
+}
+
+#[test]
+fn late_moves_are_reduced_more() {
+    // early moves and shallow nodes are left alone
+    assert_eq!(late_move_reduction(8, 1), 0);
+    assert_eq!(late_move_reduction(8, LMR_MIN_MOVES), 0);
+    assert_eq!(late_move_reduction(LMR_MIN_DRAFT - 1, 30), 0);
+    assert!(late_move_reduction(LMR_MIN_DRAFT, LMR_MIN_MOVES + 1) > 0);
+
+    // more plies left and later moves both reduce more, but always leave a ply
+    assert!(late_move_reduction(10, 20) > late_move_reduction(4, 20));
+    assert!(late_move_reduction(10, 20) > late_move_reduction(10, 4));
+    for draft in LMR_MIN_DRAFT..30 {
+        for move_number in 1..60 {
+            assert!(late_move_reduction(draft, move_number) <= draft - 2);
+        }
+    }
+}
+
+#[test]
+fn tactical_moves_are_never_reduced() {
+    let board = Board::from_fen("4k3/1P6/8/3p4/8/2N5/8/R3K3 w - - 0 1");
+    let quiet = Move::from_pcn_string("e1f1", &board);
+    let killer = Move::from_pcn_string("c3b5", &board);
+    let no_killers = [Move::NULL; 2];
+
+    assert!(can_reduce(&quiet, false, false, &no_killers));
+    assert!(!can_reduce(&Move::from_pcn_string("c3d5", &board), false, false, &no_killers));
+    assert!(!can_reduce(&Move::from_pcn_string("b7b8q", &board), false, false, &no_killers));
+    assert!(!can_reduce(&Move::from_pcn_string("a1a8", &board), false, true, &no_killers));
+    assert!(!can_reduce(&killer, false, false, &[killer, Move::NULL]));
+    assert!(!can_reduce(&quiet, true, false, &no_killers));
+}
+
+#[test]
+fn prefers_the_fastest_mate() {
+    use search::types::mate_in_moves;
+
+    // Rh8 mates at once, but there are plenty of mates in two to find along the way
+    let mut board = Board::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+    let search_hit = ab_search(&mut board, &SearchParams::new(5, WHITE)).unwrap();
+
+    assert_eq!(search_hit.move_list[0].to_pcn_string(), "h1h8");
+    assert_eq!(search_hit.score_by_color(WHITE), mate_in(1));
+    assert_eq!(mate_in_moves(search_hit.score_by_color(WHITE)), Some(1));
+
+    // black's only move walks into it
+    let mut board = Board::from_fen("k7/8/1K6/8/8/8/8/7R b - - 0 1");
+    let search_hit = ab_search(&mut board, &SearchParams::new(5, BLACK)).unwrap();
+
+    assert_eq!(search_hit.score_by_color(BLACK), mated_in(2));
+    assert_eq!(mate_in_moves(search_hit.score_by_color(BLACK)), Some(-1));
+}
+
+#[test]
+fn mates_keep_their_distance_through_the_table() {
+    let table = Arc::new(Mutex::new(Table::empty_mb(1)));
+    let search_params = SearchParams {
+        table: Some(table.clone()),
+        ..SearchParams::new(5, WHITE)
+    };
+
+    // the second search is answered from entries stored at other plies
+    for _ in 0..2 {
+        let mut board = Board::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        let search_hit = ab_search(&mut board, &search_params).unwrap();
+        assert_eq!(search_hit.score_by_color(WHITE), mate_in(1));
+    }
+}
+
+#[test]
+fn draws_by_rule_score_zero() {
+    // a queen down, white heads for the position it's already been in twice
+    let mut board = Board::from_fen("4k3/q7/8/8/8/8/8/1N5K w - - 0 1");
+    for pcn in ["b1c3", "e8d8", "c3b1", "d8e8"].iter() {
+        let mv = Move::from_pcn_string(pcn, &board);
+        make_move(&mut board, mv);
+    }
+
+    let search_hit = ab_search(&mut board, &SearchParams::new(3, WHITE)).unwrap();
+    assert_eq!(search_hit.move_list[0].to_pcn_string(), "b1c3");
+    assert_eq!(search_hit.score_by_color(WHITE), 0);
+
+    // taking the last pawn leaves nothing either side can mate with
+    let mut board = Board::from_fen("4k2b/8/8/8/8/4p3/2N5/4K3 w - - 0 1");
+    let search_hit = ab_search(&mut board, &SearchParams::new(3, WHITE)).unwrap();
+    assert_eq!(search_hit.score_by_color(WHITE), 0);
+
+    // and one move short of fifty, every line is a draw
+    let mut board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80");
+    let search_hit = ab_search(&mut board, &SearchParams::new(3, WHITE)).unwrap();
+    assert_eq!(search_hit.score_by_color(WHITE), 0);
+}
+
+// Follows the search's moves on a board of its own, checking every hook against the board the
+// search passes in.
+#[allow(dead_code)]
+struct FollowingEvaluator {
+    board: RefCell<Board>,
+    moves_made: Cell<usize>,
+}
+
+impl Evaluator for FollowingEvaluator {
+    fn evaluate(&self, board: &Board) -> Score {
+        assert_eq!(self.board.borrow().to_fen(), board.to_fen());
+        MaterialEvaluator {}.evaluate(board)
+    }
+
+    fn move_made(&self, board: &Board, mv: &Move) {
+        make_move(&mut self.board.borrow_mut(), *mv);
+        self.moves_made.set(self.moves_made.get() + 1);
+        assert_eq!(self.board.borrow().zhash, board.zhash);
+    }
+
+    fn move_unmade(&self, board: &Board, mv: &Move) {
+        unmake_move(&mut self.board.borrow_mut(), mv);
+        assert_eq!(self.board.borrow().zhash, board.zhash);
+    }
+}
+
+#[test]
+fn evaluators_plug_into_the_search() {
+    // counting material alone, the scores are the ones from before the piece-square tables
+    let search_params = SearchParams {
+        evaluator: Rc::new(MaterialEvaluator {}),
+        ..SearchParams::new(4, WHITE)
+    };
+    let mut board = Board::from_fen(SEARCH_TEST2);
+    let search_hit = ab_search(&mut board, &search_params).unwrap();
+    assert_eq!(search_hit.score_by_color(WHITE), 500);
+
+    // an evaluator that keeps its own board sees every move, null moves included, and the
+    // search leaves it back where it started
+    let evaluator = Rc::new(FollowingEvaluator {
+        board: RefCell::new(Board::from_fen(SEARCH_TEST2)),
+        moves_made: Cell::new(0),
+    });
+    let search_params = SearchParams {
+        evaluator: evaluator.clone(),
+        ..SearchParams::new(6, WHITE)
+    };
+    let mut board = Board::from_fen(SEARCH_TEST2);
+    ab_search(&mut board, &search_params).unwrap();
+
+    assert!(evaluator.moves_made.get() > 0);
+    assert_eq!(evaluator.board.borrow().to_fen(), Board::from_fen(SEARCH_TEST2).to_fen());
+}
+#[test]
+fn pawn_structure_decides_the_recapture() {
+    // Either capture wins the pawn.  Taking with the c-pawn undoubles the c-file and leaves a
+    // chain behind, while taking with the e-pawn leaves the c-pawns doubled.
+    let fen = "4k3/8/8/3p4/2P1P3/2P5/8/4K3 w - - 0 1";
+
+    let search_params = SearchParams {
+        evaluator: Rc::new(PstEvaluator {}),
+        ..SearchParams::new(3, WHITE)
+    };
+    let search_hit = ab_search(&mut Board::from_fen(fen), &search_params).unwrap();
+    assert_eq!(search_hit.move_list[0].to_pcn_string(), "e4d5");
+
+    let search_hit = ab_search(&mut Board::from_fen(fen), &SearchParams::new(3, WHITE)).unwrap();
+    assert_eq!(search_hit.move_list[0].to_pcn_string(), "c4d5");
 }
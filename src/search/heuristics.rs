@@ -0,0 +1,131 @@
+// Move ordering heuristics for quiet moves, learned from the cutoffs the search finds:
+//
+//   - killers: the last two quiet moves that caused a cutoff at each ply.  Sibling positions tend
+//     to have the same refutation, so they're tried right after the good captures.
+//   - history: a butterfly table indexed by piece and destination square, bumped for quiet moves
+//     that cut off and docked for the quiet moves tried before them.  The rest of the quiets are
+//     sorted on it.
+//
+// Both only ever change the order moves are searched in, never the result.
+
+use bitboard::square_index;
+use constants::PIECE_TYPE_COUNT;
+use moves::types::Move;
+
+// History scores are kept within +/- this by scaling each update down as the score approaches it.
+pub const MAX_HISTORY: i32 = 16384;
+
+fn is_quiet(mv: &Move) -> bool {
+    !mv.is_capture() && !mv.is_promotion()
+}
+
+// the bonus for a cutoff `draft` plies from the leaves, deeper cutoffs being worth more
+fn history_bonus(draft: u8) -> i32 {
+    (draft as i32 * draft as i32).min(MAX_HISTORY)
+}
+
+pub struct Heuristics {
+    killers: Vec<[Move; 2]>,
+    history: [[i32; 64]; PIECE_TYPE_COUNT as usize],
+}
+
+impl Heuristics {
+    pub fn new() -> Self {
+        Heuristics {
+            killers: vec![],
+            history: [[0; 64]; PIECE_TYPE_COUNT as usize],
+        }
+    }
+
+    pub fn killers(&self, ply: u8) -> [Move; 2] {
+        self.killers.get(ply as usize).cloned().unwrap_or([Move::NULL; 2])
+    }
+
+    pub fn history(&self, mv: &Move) -> i32 {
+        self.history[mv.origin_piece as usize][square_index(mv.dest_pos)]
+    }
+
+    fn update_history(&mut self, mv: &Move, bonus: i32) {
+        let entry = &mut self.history[mv.origin_piece as usize][square_index(mv.dest_pos)];
+        *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+    }
+
+    // `mv` caused a beta cutoff at `ply`, `draft` plies from the leaves, after the quiet moves in
+    // `tried` had failed to.  Captures and promotions are ordered on their own, so only quiet
+    // moves are learned from.
+    pub fn record_cutoff(&mut self, mv: &Move, ply: u8, draft: u8, tried: &[Move]) {
+        if !is_quiet(mv) {
+            return;
+        }
+
+        let ply = ply as usize;
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [Move::NULL; 2]);
+        }
+
+        let slots = &mut self.killers[ply];
+        if slots[0] != *mv {
+            slots[1] = slots[0];
+            slots[0] = *mv;
+        }
+
+        let bonus = history_bonus(draft);
+        self.update_history(mv, bonus);
+        for other in tried.iter().filter(|other| *other != mv && is_quiet(other)) {
+            self.update_history(other, -bonus);
+        }
+    }
+}
+
+impl Default for Heuristics {
+    fn default() -> Self {
+        Heuristics::new()
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use board::Board;
+
+    #[test]
+    fn cutoffs_fill_the_killer_slots() {
+        let board = Board::from_fen("4k3/8/8/3p4/8/2N5/8/4K3 w - - 0 1");
+        let first = Move::from_pcn_string("e1f1", &board);
+        let second = Move::from_pcn_string("c3b5", &board);
+        let capture = Move::from_pcn_string("c3d5", &board);
+
+        let mut heuristics = Heuristics::new();
+        assert_eq!(heuristics.killers(3), [Move::NULL; 2]);
+
+        heuristics.record_cutoff(&first, 3, 2, &[]);
+        heuristics.record_cutoff(&second, 3, 2, &[]);
+        heuristics.record_cutoff(&second, 3, 2, &[]);
+        assert_eq!(heuristics.killers(3), [second, first]);
+        assert_eq!(heuristics.killers(2), [Move::NULL; 2]);
+
+        // captures are left to MVV-LVA
+        heuristics.record_cutoff(&capture, 3, 2, &[]);
+        assert_eq!(heuristics.killers(3), [second, first]);
+        assert_eq!(heuristics.history(&capture), 0);
+    }
+
+    #[test]
+    fn history_rewards_cutoffs_and_stays_bounded() {
+        let board = Board::from_fen("4k3/8/8/8/8/2N5/8/4K3 w - - 0 1");
+        let good = Move::from_pcn_string("c3b5", &board);
+        let bad = Move::from_pcn_string("e1f1", &board);
+
+        let mut heuristics = Heuristics::new();
+        heuristics.record_cutoff(&good, 0, 4, &[bad, good]);
+        assert_eq!(heuristics.history(&good), 16);
+        assert_eq!(heuristics.history(&bad), -16);
+
+        for _ in 0..10000 {
+            heuristics.record_cutoff(&good, 0, 40, &[bad, good]);
+        }
+        assert!(heuristics.history(&good) <= MAX_HISTORY);
+        assert!(heuristics.history(&bad) >= -MAX_HISTORY);
+    }
+}
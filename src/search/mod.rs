@@ -1,3 +1,4 @@
 pub mod types;
 pub mod basic;
 pub mod observers;
+pub mod heuristics;
@@ -1,5 +1,10 @@
+#![allow(unused_imports, unused_mut, unused_variables)]
+#![allow(clippy::ptr_arg, clippy::useless_format)]
+
 //use super::types::*;
 use regex::Regex;
+use std::cell::Cell;
+use std::rc::Rc;
 //use util::*;
 use constants::*;
 use util::{opposite_color, piece_type_to_str};
@@ -33,7 +38,7 @@ pub fn print_s(_string: &String, search_depth: u8, moves: &Vec<Move>) {
     // }
 
     if search_depth <= indent_max {
-        println!("|{}{}", " ".repeat(search_depth as usize * 4), _string);  
+        eprintln!("|{}{}", " ".repeat(search_depth as usize * 4), _string);  
     }
 }
 
@@ -45,8 +50,16 @@ pub trait SearchObservable {
     fn fail_low(&self, depth: u8) {}
     fn new_best(&self, depth: u8, our_local_best: Score, moves: &Vec<Move>) {}
     fn move_scored(&self, search_hit: &SearchHit, mv: &Move, depth: u8, moves: &Vec<Move>) {}
-    fn moves_generated(&self, depth: u8, possible_moves: &Vec<Move>, move_history: &Vec<Move>) {}
     fn finished(&self, search_hit: &Option<SearchHit>) {}
+
+    // `mv` caused a beta cutoff, `move_number` moves into the node (counting from 1)
+    fn cutoff(&self, _depth: u8, _mv: &Move, _move_number: usize) {}
+
+    // a node searched every one of its `move_count` moves without a cutoff
+    fn all_moves_searched(&self, _depth: u8, _move_count: usize) {}
+
+    // passing the turn was enough to get to `their_best`, so the node wasn't searched
+    fn null_move_cutoff(&self, _depth: u8, _their_best: Score) {}
 }
 
 pub struct SearchLogger { }
@@ -83,10 +96,6 @@ impl SearchObservable for SearchLogger {
         print_s(&format!("[{}] Move {:?} has score {:?}", depth, mv, search_hit), depth, moves);
     }
 
-    fn moves_generated(&self, depth: u8, possible_moves: &Vec<Move>, move_history: &Vec<Move>) {
-        print_s(&format!("[{}] Possible moves: {:?}", depth, possible_moves), depth, move_history);
-    }
-    
     fn finished(&self, search_hit: &Option<SearchHit>) {
         if search_hit.is_some() {
             println!("Best move found: {:?}", search_hit.as_ref());
@@ -101,3 +110,93 @@ impl SearchObservable for SearchLogger {
 pub struct NoopObserver { }
 
 impl SearchObservable for NoopObserver {}
+
+// Counts the nodes visited, which the caller reads through the shared cell.
+pub struct NodeCounter {
+    pub nodes: Rc<Cell<usize>>,
+}
+
+impl SearchObservable for NodeCounter {
+    fn search_start(&self, _frame_color: Color, _board: &Board) {
+        self.nodes.set(self.nodes.get() + 1);
+    }
+
+    fn recursed(&self, _frame_color: Color, _depth: u8, _our_best: Score, _their_best: Score, _move_list: &Vec<Move>) {
+        self.nodes.set(self.nodes.get() + 1);
+    }
+}
+
+// How often interior nodes cut off, and how early.  A well ordered search cuts off at most nodes
+// that it cuts off at all on the first move.
+pub struct CutoffStats {
+    pub nodes: Cell<usize>,
+    pub cutoffs: Cell<usize>,
+    pub first_move_cutoffs: Cell<usize>,
+
+    // nodes that didn't need searching because a null move already cut off, not counted in `nodes`
+    pub null_move_cutoffs: Cell<usize>,
+
+    // summed over every cutoff, so dividing by `cutoffs` gives how far in they come on average
+    pub cutoff_move_numbers: Cell<usize>,
+}
+
+impl CutoffStats {
+    pub fn new() -> Self {
+        CutoffStats {
+            nodes: Cell::new(0),
+            cutoffs: Cell::new(0),
+            first_move_cutoffs: Cell::new(0),
+            null_move_cutoffs: Cell::new(0),
+            cutoff_move_numbers: Cell::new(0),
+        }
+    }
+
+    fn ratio(count: usize, total: usize) -> f64 {
+        if total == 0 { 0.0 } else { count as f64 / total as f64 }
+    }
+
+    // the share of interior nodes that cut off
+    pub fn cutoff_rate(&self) -> f64 {
+        CutoffStats::ratio(self.cutoffs.get(), self.nodes.get())
+    }
+
+    // the share of cutoffs that came on the first move searched
+    pub fn first_move_rate(&self) -> f64 {
+        CutoffStats::ratio(self.first_move_cutoffs.get(), self.cutoffs.get())
+    }
+
+    pub fn average_cutoff_move(&self) -> f64 {
+        CutoffStats::ratio(self.cutoff_move_numbers.get(), self.cutoffs.get())
+    }
+}
+
+impl Default for CutoffStats {
+    fn default() -> Self {
+        CutoffStats::new()
+    }
+}
+
+// Fills in the shared stats, which the caller reads once the search is done.
+pub struct CutoffCounter {
+    pub stats: Rc<CutoffStats>,
+}
+
+impl SearchObservable for CutoffCounter {
+    fn cutoff(&self, _depth: u8, _mv: &Move, move_number: usize) {
+        let stats = &self.stats;
+        stats.nodes.set(stats.nodes.get() + 1);
+        stats.cutoffs.set(stats.cutoffs.get() + 1);
+        stats.cutoff_move_numbers.set(stats.cutoff_move_numbers.get() + move_number);
+        if move_number == 1 {
+            stats.first_move_cutoffs.set(stats.first_move_cutoffs.get() + 1);
+        }
+    }
+
+    fn all_moves_searched(&self, _depth: u8, _move_count: usize) {
+        self.stats.nodes.set(self.stats.nodes.get() + 1);
+    }
+
+    fn null_move_cutoff(&self, _depth: u8, _their_best: Score) {
+        self.stats.null_move_cutoffs.set(self.stats.null_move_cutoffs.get() + 1);
+    }
+}
@@ -1,10 +1,22 @@
+#![allow(unused_imports, unused_parens)]
+#![allow(clippy::clone_on_copy)]
+
 use moves::types::{Move};
+use engine::table::Table;
+use search::heuristics::Heuristics;
+use eval::{Evaluator, StandardEvaluator};
 use constants::{Color, WHITE, BLACK};
 use std::fmt;
+use std::cell::RefCell;
+use std::rc::Rc;
 use util::{opposite_color};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 
 pub type Score = i16;
 
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum SearchStyle {
     BASIC,
 }
@@ -12,10 +24,114 @@ pub enum SearchStyle {
 pub const MIN_SCORE: i16 = -32767;
 pub const MAX_SCORE: i16 = 32767;
 
+// Mate scores count down from MATE_SCORE by the plies from the root to the mate, so a faster mate
+// always scores higher and a slower loss always scores less badly.  Anything at least MATE_BOUND
+// away from zero is a mate.
+pub const MATE_SCORE: Score = MAX_SCORE;
+pub const MAX_MATE_PLY: Score = 256;
+pub const MATE_BOUND: Score = MATE_SCORE - MAX_MATE_PLY;
+
+// the score for the side giving mate `ply` plies from the root
+pub fn mate_in(ply: u8) -> Score {
+    MATE_SCORE - ply as Score
+}
+
+// the score for the side that is mated `ply` plies from the root
+pub fn mated_in(ply: u8) -> Score {
+    -MATE_SCORE + ply as Score
+}
+
+pub fn is_mate_score(score: Score) -> bool {
+    score.abs() >= MATE_BOUND
+}
+
+// The mate a score stands for in whole moves, the way uci's "score mate" counts them.  Negative
+// when it's the side the score is for that gets mated.
+pub fn mate_in_moves(score: Score) -> Option<i16> {
+    if !is_mate_score(score) {
+        None
+    } else if score > 0 {
+        Some((MATE_SCORE - score + 1) / 2)
+    } else {
+        Some(-(MATE_SCORE + score) / 2)
+    }
+}
+
+// The table is shared by every path to a position, so mates are stored as plies from the position
+// itself, and turned back into plies from the root when read at a given `ply`.
+pub fn score_to_table(score: Score, ply: u8) -> Score {
+    if score >= MATE_BOUND {
+        score + ply as Score
+    } else if score <= -MATE_BOUND {
+        score - ply as Score
+    } else {
+        score
+    }
+}
+
+pub fn score_from_table(score: Score, ply: u8) -> Score {
+    if score >= MATE_BOUND {
+        score - ply as Score
+    } else if score <= -MATE_BOUND {
+        score + ply as Score
+    } else {
+        score
+    }
+}
+
 pub struct SearchParams {
     pub search_ply_target: u8,
     pub orig_to_move: Color,
     pub search_style: SearchStyle,
+
+    // The search gives up as soon as either of these trips, returning whatever it has so far.
+    // Callers should treat that result as incomplete.
+    pub should_stop: Option<Arc<AtomicBool>>,
+    pub deadline: Option<Instant>,
+
+    // shared between iterations, and between searches when the engine keeps it around
+    pub table: Option<Arc<Mutex<Table>>>,
+
+    // killers and history, shared between iterations of the same search
+    pub heuristics: Rc<RefCell<Heuristics>>,
+
+    // scores the leaves, and quiescence's stand pat
+    pub evaluator: Rc<dyn Evaluator>,
+}
+
+impl SearchParams {
+    pub fn new(search_ply_target: u8, orig_to_move: Color) -> Self {
+        SearchParams {
+            search_ply_target,
+            orig_to_move,
+            search_style: SearchStyle::BASIC,
+            should_stop: None,
+            deadline: None,
+            table: None,
+            heuristics: Rc::new(RefCell::new(Heuristics::new())),
+            evaluator: Rc::new(StandardEvaluator::new()),
+        }
+    }
+
+    // The same search with `plies` fewer to go, for searching a subtree at reduced depth.  Everything
+    // else, including the table, the heuristics and the evaluator, is shared with this one.
+    pub fn reduced(&self, plies: u8) -> SearchParams {
+        SearchParams {
+            search_ply_target: self.search_ply_target - plies,
+            orig_to_move: self.orig_to_move,
+            search_style: self.search_style,
+            should_stop: self.should_stop.clone(),
+            deadline: self.deadline,
+            table: self.table.clone(),
+            heuristics: self.heuristics.clone(),
+            evaluator: self.evaluator.clone(),
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.should_stop.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed))
+            || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
 }
 
 pub struct SearchResults {
@@ -79,3 +195,43 @@ impl fmt::Debug for SearchHit {
     }
 }
 
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn mate_scores_count_moves() {
+        // mating on our first move, or being mated on their first
+        assert_eq!(mate_in_moves(mate_in(1)), Some(1));
+        assert_eq!(mate_in_moves(mated_in(2)), Some(-1));
+        assert_eq!(mate_in_moves(mate_in(5)), Some(3));
+        assert_eq!(mate_in_moves(mated_in(0)), Some(0));
+        assert_eq!(mate_in_moves(12), None);
+
+        assert!(mate_in(3) > mate_in(5));
+        assert!(mated_in(6) > mated_in(2));
+    }
+
+    #[test]
+    fn table_scores_round_trip() {
+        for &score in [mate_in(9), mated_in(8), 0, -7, MATE_BOUND - 1].iter() {
+            assert_eq!(score_from_table(score_to_table(score, 4), 4), score);
+        }
+
+        assert_eq!(score_to_table(mate_in(9), 4), mate_in(5));
+        assert_eq!(score_from_table(mate_in(5), 2), mate_in(7));
+        assert_eq!(score_to_table(17, 4), 17);
+    }
+
+    #[test]
+    fn reduced_keeps_everything_but_the_depth() {
+        let params = SearchParams::new(6, WHITE);
+        let reduced = params.reduced(2);
+
+        assert_eq!(reduced.search_ply_target, 4);
+        assert_eq!(reduced.search_style, params.search_style);
+        assert!(Rc::ptr_eq(&reduced.heuristics, &params.heuristics));
+        assert!(Rc::ptr_eq(&reduced.evaluator, &params.evaluator));
+    }
+}
@@ -1,3 +1,6 @@
+#![allow(unused_mut, unused_variables)]
+#![allow(clippy::manual_range_contains, clippy::redundant_pattern)]
+
 use std::fmt;
 use constants::*;
 use util::{is_white, file_to_char, char_to_file, piece_type_to_char, char_to_piece_type};
@@ -1,3 +1,6 @@
+#![allow(unused_imports, unused_mut)]
+#![allow(clippy::assertions_on_constants, clippy::int_plus_one, clippy::len_zero, clippy::manual_range_contains, clippy::map_clone, clippy::match_ref_pats, clippy::needless_late_init, clippy::ptr_arg, clippy::unnecessary_cast)]
+
 use constants::{
     FILE_COUNT, RANK_COUNT,
     WHITE, 
@@ -19,7 +22,7 @@ use std::collections;
 
 //TODO: are any of these functions candidates for inlining?
 pub fn is_white(piece: PieceType) -> bool {
-    piece >= W_PAWN && piece <= W_KING
+    piece <= W_KING
 }
 
 pub fn color_of(piece: PieceType) -> Color {
@@ -31,7 +34,7 @@ pub fn opposite_color(color: Color) -> Color {
 }
 
 pub fn to_white(piece: PieceType) -> PieceType {
-    if piece >= W_PAWN && piece <= W_KING {
+    if piece <= W_KING {
         piece 
     } else {
         opposite_color_piece_type(piece)
@@ -47,7 +50,7 @@ pub fn to_black(piece: PieceType) -> PieceType {
 }
 
 pub fn to_color(piece: PieceType, should_be_white: bool) -> PieceType {
-    let piece_is_white: bool = piece >= W_PAWN && piece <= W_KING;
+    let piece_is_white: bool = piece <= W_KING;
     
     if (should_be_white && piece_is_white) || (!should_be_white && !piece_is_white) {
         piece 
@@ -65,7 +68,7 @@ pub fn to_color(piece: PieceType, should_be_white: bool) -> PieceType {
 // }
 
 pub fn opposite_color_piece_type(piece_type: PieceType) -> PieceType {
-    if piece_type >= W_PAWN && piece_type <= W_KING {
+    if piece_type <= W_KING {
         piece_type + PIECE_TYPE_COLOR_OFFSET
     } else if piece_type >= B_PAWN && piece_type <= B_KING {
         piece_type - PIECE_TYPE_COLOR_OFFSET
@@ -81,7 +84,7 @@ pub fn is_occupied_and_enemy(piece1: PieceType, piece2: PieceType) -> bool {
 }
 
 pub fn is_same_color(piece1: PieceType, piece2: PieceType) -> bool {
-    (piece1 >= W_PAWN && piece1 <= W_KING) && (piece2 >= W_PAWN && piece2 <= W_KING)
+    (piece1 <= W_KING) && (piece2 <= W_KING)
         || (piece1 >= B_PAWN && piece1 <= B_KING) && (piece2 >= B_PAWN && piece2 <= B_KING)
 }
 